@@ -377,7 +377,10 @@ async function seedChannel(sequencerUrl: string, channel: ChannelView): Promise<
       channelId: channel.channelId,
       owner: channel.owner,
       balance: channel.balance,
-      expiryTimestamp: channel.expiryTimestamp
+      expiryTimestamp: channel.expiryTimestamp,
+      ...(channel.userSignature
+        ? { userSignature: channel.userSignature, signatureTimestamp: channel.signatureTimestamp }
+        : {})
     })
   });
   if (!resp.ok) {
@@ -521,9 +524,9 @@ async function ensureChannel(
     balance: channelAmount.toString(),
     expiryTimestamp: expiry,
     sequenceNumber: 0,
-    userSignature: "",
+    userSignature,
     sequencerSignature: "",
-    signatureTimestamp: 0,
+    signatureTimestamp: now,
     recipients: []
   };
 
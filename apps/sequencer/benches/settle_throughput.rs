@@ -0,0 +1,168 @@
+//! Settle throughput across many channels as the number of cores grows.
+//!
+//! Drives the same path as `service::settle` (per-channel lock, validation, signature
+//! recovery and co-signing on the blocking pool) but skips the Postgres write, so the
+//! numbers isolate lock contention and crypto cost.
+//!
+//! `cargo bench --bench settle_throughput -- [channels] [settles-per-channel]`
+
+use std::{
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use cpc_sequencer::{
+    cache::{ChannelCache, ChannelHandle},
+    config::Config,
+    crypto::sign_update,
+    model::{ChannelState, ChannelStatus, PayInChannelRequest, RecipientBalance},
+    service::{run_blocking, sign_next_state},
+};
+use ethers_core::types::{Address, H256, U256};
+use ethers_signers::{LocalWallet, Signer};
+
+const CHANNEL_MANAGER: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+const SEQUENCER_KEY: &str = "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+const DEFAULT_CHANNELS: usize = 64;
+const DEFAULT_SETTLES_PER_CHANNEL: u64 = 20;
+const AMOUNT: u64 = 100;
+
+struct Fixture {
+    channel: ChannelState,
+    payments: Vec<PayInChannelRequest>,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1).filter(|arg| arg != "--bench");
+    let channels = args.next().and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_CHANNELS);
+    let settles = args
+        .next()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_SETTLES_PER_CHANNEL);
+
+    std::env::set_var("CHANNEL_MANAGER_ADDRESS", CHANNEL_MANAGER);
+    std::env::set_var("SEQUENCER_PRIVATE_KEY", SEQUENCER_KEY);
+    let config = Arc::new(Config::from_env().expect("bench config"));
+    let sequencer_wallet = SEQUENCER_KEY
+        .parse::<LocalWallet>()
+        .expect("sequencer key")
+        .with_chain_id(config.chain_id);
+
+    println!("preparing {channels} channels x {settles} signed updates...");
+    let fixtures: Arc<Vec<Fixture>> = Arc::new((0..channels).map(|i| fixture(i, settles, &config)).collect());
+
+    let max_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut thread_counts = vec![1];
+    while thread_counts.last().copied().unwrap_or(1) * 2 <= max_threads {
+        thread_counts.push(thread_counts.last().copied().unwrap_or(1) * 2);
+    }
+    if thread_counts.last() != Some(&max_threads) {
+        thread_counts.push(max_threads);
+    }
+
+    println!("{:>8} {:>12} {:>10}", "threads", "settles/s", "speedup");
+    let mut baseline = None;
+    for threads in thread_counts {
+        let throughput = run(threads, fixtures.clone(), config.clone(), sequencer_wallet.clone());
+        let base = *baseline.get_or_insert(throughput);
+        println!("{threads:>8} {throughput:>12.0} {:>9.2}x", throughput / base);
+    }
+}
+
+fn run(threads: usize, fixtures: Arc<Vec<Fixture>>, config: Arc<Config>, wallet: LocalWallet) -> f64 {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(threads)
+        .max_blocking_threads(threads)
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+
+    runtime.block_on(async move {
+        let cache = ChannelCache::new(fixtures.len());
+        let handles: Vec<ChannelHandle> = fixtures.iter().map(|f| cache.insert(f.channel.clone())).collect();
+
+        let started = Instant::now();
+        let mut tasks = Vec::with_capacity(handles.len());
+        for (index, handle) in handles.into_iter().enumerate() {
+            let fixtures = fixtures.clone();
+            let config = config.clone();
+            let wallet = wallet.clone();
+            tasks.push(tokio::spawn(async move {
+                for payment in &fixtures[index].payments {
+                    let mut channel = handle.lock().await;
+                    let current = channel.clone();
+                    let payload = payment.clone();
+                    let config = config.clone();
+                    let wallet = wallet.clone();
+                    let updated = run_blocking(move || sign_next_state(&current, &payload, &config, &wallet))
+                        .await
+                        .expect("settle");
+                    *channel = updated;
+                }
+            }));
+        }
+        for task in tasks {
+            task.await.expect("settle task");
+        }
+
+        let total = fixtures.iter().map(|f| f.payments.len()).sum::<usize>();
+        total as f64 / started.elapsed().as_secs_f64()
+    })
+}
+
+fn fixture(index: usize, settles: u64, config: &Config) -> Fixture {
+    let owner = format!("{:064x}", index + 1)
+        .parse::<LocalWallet>()
+        .expect("owner key")
+        .with_chain_id(config.chain_id);
+    let receiver = Address::from_low_u64_be(0xbeef);
+    let channel_id = H256::from_low_u64_be(index as u64 + 1);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).expect("clock").as_secs();
+
+    let channel = ChannelState {
+        channel_id,
+        owner: owner.address(),
+        balance: U256::from(AMOUNT * (settles + 1)),
+        expiry_ts: now + 3600,
+        sequence_number: 0,
+        user_signature: String::new(),
+        sequencer_signature: String::new(),
+        signature_timestamp: 0,
+        recipients: Vec::new(),
+        status: ChannelStatus::Open,
+        close_tx_hash: None,
+        closed_at: None,
+    };
+
+    let payments = (1..=settles)
+        .map(|sequence_number| {
+            let recipients = [RecipientBalance {
+                recipient_address: receiver,
+                balance: U256::from(AMOUNT * sequence_number),
+                position: 0,
+            }];
+            let user_signature = sign_update(
+                &owner,
+                channel_id,
+                sequence_number,
+                now,
+                &recipients,
+                config.chain_id,
+                config.channel_manager,
+            )
+            .expect("user signature");
+            PayInChannelRequest {
+                channel_id: format!("0x{:x}", channel_id),
+                amount: AMOUNT.to_string(),
+                receiver: format!("0x{:x}", receiver),
+                sequence_number,
+                timestamp: now,
+                user_signature,
+                purpose: None,
+                fee_for_payment: None,
+            }
+        })
+        .collect();
+
+    Fixture { channel, payments }
+}
@@ -0,0 +1,100 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use crate::{
+    db::{archive_channels, expired_channels_owing, purge_archive},
+    error::AppError,
+    model::FinalizeChannelRequest,
+    service::{finalize_channel, AppState},
+};
+
+const ARCHIVE_BATCH_SIZE: usize = 500;
+/// Most expired channels a sweep sends close transactions for.
+const EXPIRED_FINALIZE_BATCH_SIZE: usize = 20;
+
+/// Moves finalized and expired channels out of the live tables and the channel cache.
+///
+/// Runs on the leader only: a channel becomes eligible `archive_after` past its close (or
+/// its expiry), is copied with its final signed state and close transaction into
+/// `archived_channels`/`archived_recipients`, and can still be read through
+/// `GET /channel/:id?includeArchived=true`. With a `retention`, archived channels older
+/// than that are deleted for good.
+///
+/// An expired channel that still owes recipients is never archived as is: after expiry
+/// only a sequencer close pays balances that were not published on-chain, so the sweep
+/// finalizes it first and archives it once it is closed.
+pub struct Archiver {
+    pub state: AppState,
+    pub interval: Duration,
+    pub archive_after: Duration,
+    pub retention: Option<Duration>,
+}
+
+impl Archiver {
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            // Standbys and replicas skip the sweep; the leader archives for everyone.
+            let Ok(fencing_token) = self.state.leadership.ensure_leader() else {
+                continue;
+            };
+            if let Err(err) = self.finalize_expired().await {
+                warn!(error = %err, "closing expired channels failed");
+            }
+            if let Err(err) = self.sweep(fencing_token).await {
+                warn!(error = %err, "channel archival failed");
+            }
+        }
+    }
+
+    async fn finalize_expired(&self) -> Result<(), AppError> {
+        let channel_ids = expired_channels_owing(&self.state.db, now_secs(), EXPIRED_FINALIZE_BATCH_SIZE).await?;
+        for channel_id in channel_ids {
+            let request = FinalizeChannelRequest {
+                channel_id: channel_id.clone(),
+            };
+            match finalize_channel(&self.state, request).await {
+                Ok(response) => {
+                    info!(channel_id = %channel_id, transaction_hash = %response.transaction_hash, "expired channel finalized")
+                }
+                Err(err) => warn!(channel_id = %channel_id, error = %err, "could not finalize expired channel"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn sweep(&self, fencing_token: Option<u64>) -> Result<(), AppError> {
+        let db = &self.state.db;
+        let now = now_secs();
+        let cutoff = now.saturating_sub(self.archive_after.as_secs());
+        let mut archived = 0;
+        loop {
+            let channel_ids = archive_channels(db, cutoff, now, ARCHIVE_BATCH_SIZE, fencing_token).await?;
+            for channel_id in &channel_ids {
+                self.state.channels.remove(channel_id).await;
+            }
+            archived += channel_ids.len();
+            if channel_ids.len() < ARCHIVE_BATCH_SIZE {
+                break;
+            }
+        }
+
+        let purged = match self.retention {
+            Some(retention) => purge_archive(db, now.saturating_sub(retention.as_secs())).await?,
+            None => 0,
+        };
+        if archived > 0 || purged > 0 {
+            info!(archived, purged, "channel archival sweep");
+        }
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
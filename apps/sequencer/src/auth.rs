@@ -0,0 +1,490 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::Response,
+    routing::MethodRouter,
+};
+use ethers_core::{types::Address, utils::hex};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use subtle::ConstantTimeEq;
+use tracing::warn;
+
+use crate::{
+    crypto::parse_address,
+    db::{load_active_api_keys, load_active_receiver_bindings},
+    error::AppError,
+    model::AuthMetrics,
+    session::{Session, SessionKeys, SESSION_TOKEN_PREFIX},
+};
+
+/// Headers of an HMAC-signed request.
+pub const KEY_ID_HEADER: &str = "x-api-key-id";
+pub const TIMESTAMP_HEADER: &str = "x-api-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-api-signature";
+
+/// Access level a route requires. Roles are ordered: a key satisfies every role up to
+/// its own, so an admin key can call resource-server and read-only routes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// No credentials needed; the request is authorized by its own signatures.
+    Public,
+    /// A SIWE wallet session or any API key; the handler decides what the caller may see
+    /// (see `Reader`).
+    Wallet,
+    ReadOnly,
+    /// Resource servers validating and settling payments on behalf of receivers.
+    ResourceServer,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Public => "public",
+            Role::Wallet => "wallet",
+            Role::ReadOnly => "read-only",
+            Role::ResourceServer => "resource-server",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parses a role keys can be issued with; `public` and `wallet` are not among them.
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        match input {
+            "read-only" => Ok(Role::ReadOnly),
+            "resource-server" => Ok(Role::ResourceServer),
+            "admin" => Ok(Role::Admin),
+            other => Err(AppError::bad_request(format!(
+                "invalid role: {other} (expected admin, resource-server or read-only)"
+            ))),
+        }
+    }
+}
+
+/// Key that authenticated a request, added to the request extensions.
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub key_id: String,
+    pub role: Role,
+}
+
+struct ApiKey {
+    secret: String,
+    role: Role,
+}
+
+/// Checks API credentials against the keys in Postgres.
+///
+/// Keys are cached in memory and reloaded every refresh interval, so creating or revoking
+/// a key through the CLI takes effect within one interval. A request authenticates either
+/// with `Authorization: Bearer <keyId>.<secret>`, or by signing it: `X-Api-Signature` is
+/// the hex HMAC-SHA256, keyed with the secret, of
+/// `METHOD\npath?query\ntimestamp\nhex(sha256(body))`. Signed requests must carry a
+/// timestamp within the replay window and each signature is accepted once.
+///
+/// Resource-server keys may only move payments to receivers bound to them (see
+/// `service::bind_receiver`); bindings are cached and refreshed with the keys.
+pub struct Authenticator {
+    enabled: bool,
+    replay_window: u64,
+    /// Largest body a signed request may carry (`MAX_BODY_BYTES`); it is buffered to be hashed
+    /// before the route's own body limit applies.
+    max_body_bytes: usize,
+    keys: RwLock<HashMap<String, ApiKey>>,
+    bindings: RwLock<HashMap<String, HashSet<Address>>>,
+    /// Signatures accepted within the replay window, with their timestamps.
+    seen_signatures: Mutex<HashMap<String, u64>>,
+    rejected_payments: Mutex<BTreeMap<String, u64>>,
+    sessions: Option<SessionKeys>,
+}
+
+impl Authenticator {
+    /// Lets every request through; used when `AUTH_ENABLED` is off.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            replay_window: 0,
+            max_body_bytes: 0,
+            keys: RwLock::new(HashMap::new()),
+            bindings: RwLock::new(HashMap::new()),
+            seen_signatures: Mutex::new(HashMap::new()),
+            rejected_payments: Mutex::new(BTreeMap::new()),
+            sessions: None,
+        }
+    }
+
+    pub async fn load(
+        db: &PgPool,
+        replay_window_secs: u64,
+        max_body_bytes: usize,
+        sessions: SessionKeys,
+    ) -> Result<Self, sqlx::Error> {
+        let auth = Self {
+            enabled: true,
+            replay_window: replay_window_secs,
+            max_body_bytes,
+            keys: RwLock::new(HashMap::new()),
+            bindings: RwLock::new(HashMap::new()),
+            seen_signatures: Mutex::new(HashMap::new()),
+            rejected_payments: Mutex::new(BTreeMap::new()),
+            sessions: Some(sessions),
+        };
+        auth.refresh(db).await?;
+        Ok(auth)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Replaces the cached keys and receiver bindings with the active ones in Postgres,
+    /// returning how many keys there are. Rows with an unknown role are skipped.
+    pub async fn refresh(&self, db: &PgPool) -> Result<usize, sqlx::Error> {
+        let mut keys = HashMap::new();
+        for (key_id, secret, role) in load_active_api_keys(db).await? {
+            match Role::parse(&role) {
+                Ok(role) => {
+                    keys.insert(key_id, ApiKey { secret, role });
+                }
+                Err(err) => warn!(key_id = %key_id, error = %err, "skipping api key"),
+            }
+        }
+        let mut bindings: HashMap<String, HashSet<Address>> = HashMap::new();
+        for (key_id, receiver) in load_active_receiver_bindings(db).await? {
+            if let Ok(receiver) = parse_address(&receiver) {
+                bindings.entry(key_id).or_default().insert(receiver);
+            }
+        }
+        let loaded = keys.len();
+        *self.keys.write().expect("api keys poisoned") = keys;
+        *self.bindings.write().expect("receiver bindings poisoned") = bindings;
+        Ok(loaded)
+    }
+
+    /// Issues a session token for a wallet that signed in, with its expiry.
+    pub fn issue_session(&self, address: Address) -> Result<(String, u64), AppError> {
+        let sessions = self
+            .sessions
+            .as_ref()
+            .ok_or_else(|| AppError::bad_request("wallet sessions need AUTH_ENABLED"))?;
+        Ok(sessions.issue(address, now_secs()))
+    }
+
+    /// Caches a binding stored by this process; other processes see it on their next refresh.
+    pub fn bind(&self, key_id: &str, receiver: Address) {
+        self.bindings
+            .write()
+            .expect("receiver bindings poisoned")
+            .entry(key_id.to_string())
+            .or_default()
+            .insert(receiver);
+    }
+
+    pub fn unbind(&self, key_id: &str, receiver: Address) {
+        if let Some(receivers) = self.bindings.write().expect("receiver bindings poisoned").get_mut(key_id) {
+            receivers.remove(&receiver);
+        }
+    }
+
+    /// Checks that the caller may direct a payment to `payee`. Admin keys may pay anyone;
+    /// without authentication there is no caller and every payee is allowed.
+    pub fn check_payee(&self, caller: Option<&Caller>, payee: Address) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let caller = caller.ok_or_else(|| AppError::unauthorized("missing api credentials"))?;
+        if caller.role == Role::Admin {
+            return Ok(());
+        }
+        let bound = self
+            .bindings
+            .read()
+            .expect("receiver bindings poisoned")
+            .get(&caller.key_id)
+            .is_some_and(|receivers| receivers.contains(&payee));
+        if !bound {
+            return Err(AppError::forbidden(format!(
+                "api key {} is not bound to receiver 0x{:x}",
+                caller.key_id, payee
+            )));
+        }
+        Ok(())
+    }
+
+    /// Logs a refused payment and counts it against the caller's key.
+    pub fn record_rejection(&self, caller: Option<&Caller>, action: &str, reason: &str) {
+        let key_id = caller.map(|caller| caller.key_id.as_str()).unwrap_or("anonymous");
+        warn!(key_id, action, reason, "payment rejected");
+        *self
+            .rejected_payments
+            .lock()
+            .expect("rejected payments poisoned")
+            .entry(key_id.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn metrics(&self) -> AuthMetrics {
+        AuthMetrics {
+            enabled: self.enabled,
+            active_keys: self.keys.read().expect("api keys poisoned").len(),
+            rejected_payments: self.rejected_payments.lock().expect("rejected payments poisoned").clone(),
+        }
+    }
+
+    /// Reloads the keys every `interval` until the process exits.
+    pub async fn run_refresh(self: Arc<Self>, db: PgPool, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.refresh(&db).await {
+                warn!(error = %err, "api key refresh failed");
+            }
+        }
+    }
+
+    fn bearer(&self, token: &str) -> Result<Caller, AppError> {
+        let (key_id, secret) = token
+            .split_once('.')
+            .ok_or_else(|| AppError::unauthorized("malformed api key"))?;
+        let keys = self.keys.read().expect("api keys poisoned");
+        let key = keys.get(key_id).ok_or_else(|| AppError::unauthorized("unknown api key"))?;
+        if !bool::from(key.secret.as_bytes().ct_eq(secret.as_bytes())) {
+            return Err(AppError::unauthorized("invalid api key"));
+        }
+        Ok(Caller {
+            key_id: key_id.to_string(),
+            role: key.role,
+        })
+    }
+
+    fn signed(&self, headers: &HeaderMap, method: &str, path: &str, body: &[u8]) -> Result<Caller, AppError> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| AppError::unauthorized(format!("missing {name} header")))
+        };
+        let key_id = header(KEY_ID_HEADER)?;
+        let timestamp = header(TIMESTAMP_HEADER)?;
+        let signature = header(SIGNATURE_HEADER)?;
+
+        let signed_at: u64 = timestamp
+            .parse()
+            .map_err(|_| AppError::unauthorized("invalid request timestamp"))?;
+        let now = now_secs();
+        if signed_at.abs_diff(now) > self.replay_window {
+            return Err(AppError::unauthorized("request timestamp outside the replay window"));
+        }
+        let signature_bytes = hex::decode(signature.trim_start_matches("0x"))
+            .map_err(|_| AppError::unauthorized("invalid request signature"))?;
+
+        let role = {
+            let keys = self.keys.read().expect("api keys poisoned");
+            let key = keys.get(key_id).ok_or_else(|| AppError::unauthorized("unknown api key"))?;
+            let mut mac = Hmac::<Sha256>::new_from_slice(key.secret.as_bytes()).expect("hmac accepts any key length");
+            mac.update(signing_payload(method, path, timestamp, body).as_bytes());
+            mac.verify_slice(&signature_bytes)
+                .map_err(|_| AppError::unauthorized("invalid request signature"))?;
+            key.role
+        };
+
+        let mut seen = self.seen_signatures.lock().expect("seen signatures poisoned");
+        seen.retain(|_, ts| ts.abs_diff(now) <= self.replay_window);
+        if seen.insert(hex::encode(&signature_bytes), signed_at).is_some() {
+            return Err(AppError::unauthorized("request signature already used"));
+        }
+        Ok(Caller {
+            key_id: key_id.to_string(),
+            role,
+        })
+    }
+
+    /// Authenticates a request and checks it against `role`. Signed requests have their
+    /// body buffered for hashing and put back afterwards.
+    async fn authorize(&self, role: Role, request: Request) -> Result<Request, AppError> {
+        let bearer = request
+            .headers()
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::to_string);
+
+        let (caller, mut request) = if let Some(token) = bearer {
+            let token = token.trim();
+            if token.starts_with(SESSION_TOKEN_PREFIX) {
+                return self.session(role, token, request);
+            }
+            (self.bearer(token)?, request)
+        } else if request.headers().contains_key(SIGNATURE_HEADER) {
+            let (parts, body) = request.into_parts();
+            let body = to_bytes(body, self.max_body_bytes)
+                .await
+                .map_err(|err| AppError::bad_request(format!("invalid request body: {err}")))?;
+            let path = parts
+                .uri
+                .path_and_query()
+                .map(|path| path.as_str())
+                .unwrap_or_else(|| parts.uri.path());
+            let caller = self.signed(&parts.headers, parts.method.as_str(), path, &body)?;
+            (caller, Request::from_parts(parts, Body::from(body)))
+        } else {
+            return Err(AppError::unauthorized("missing api credentials"));
+        };
+
+        if caller.role < role {
+            return Err(AppError::forbidden(format!(
+                "api key {} has role {}, route requires {}",
+                caller.key_id,
+                caller.role.as_str(),
+                role.as_str()
+            )));
+        }
+        request.extensions_mut().insert(caller);
+        Ok(request)
+    }
+
+    fn session(&self, role: Role, token: &str, mut request: Request) -> Result<Request, AppError> {
+        let sessions = self
+            .sessions
+            .as_ref()
+            .ok_or_else(|| AppError::unauthorized("wallet sessions are not enabled"))?;
+        let session = sessions.verify(token, now_secs())?;
+        if role > Role::Wallet {
+            return Err(AppError::forbidden(format!(
+                "wallet sessions only grant channel reads, route requires {}",
+                role.as_str()
+            )));
+        }
+        request.extensions_mut().insert(session);
+        Ok(request)
+    }
+}
+
+/// Who a channel read on a `Role::Wallet` route is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reader {
+    /// Admin key, or authentication disabled: sees everything.
+    Operator,
+    /// Signed-in wallet: sees channels it owns in full and channels paying it redacted.
+    Wallet(Address),
+}
+
+impl Reader {
+    /// Keys below admin pass the route's role check but may not read other parties' channels.
+    pub fn resolve(auth: &Authenticator, caller: Option<&Caller>, session: Option<&Session>) -> Result<Self, AppError> {
+        if !auth.is_enabled() {
+            return Ok(Reader::Operator);
+        }
+        if let Some(session) = session {
+            return Ok(Reader::Wallet(session.address));
+        }
+        match caller {
+            Some(caller) if caller.role == Role::Admin => Ok(Reader::Operator),
+            Some(caller) => Err(AppError::forbidden(format!(
+                "channel reads need a wallet session or an admin key, api key {} has role {}",
+                caller.key_id,
+                caller.role.as_str()
+            ))),
+            None => Err(AppError::unauthorized("missing credentials")),
+        }
+    }
+
+    /// Checks that the reader is `address` itself (or an operator).
+    pub fn ensure(self, address: Address) -> Result<(), AppError> {
+        match self {
+            Reader::Wallet(wallet) if wallet != address => {
+                Err(AppError::forbidden(format!("session of 0x{:x} cannot read 0x{:x}", wallet, address)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// String a signed request's HMAC is computed over.
+pub fn signing_payload(method: &str, path: &str, timestamp: &str, body: &[u8]) -> String {
+    format!("{method}\n{path}\n{timestamp}\n{}", hex::encode(Sha256::digest(body)))
+}
+
+/// Declares the role a route needs. Public routes are left as they are; every other
+/// route is wrapped in a middleware that rejects requests without a key of that role
+/// (401 without valid credentials, 403 with a key of a lower role).
+pub fn require<S>(role: Role, auth: &Arc<Authenticator>, route: MethodRouter<S>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if role == Role::Public || !auth.is_enabled() {
+        return route;
+    }
+    route.route_layer(middleware::from_fn_with_state((auth.clone(), role), authorize))
+}
+
+async fn authorize(
+    State((auth, role)): State<(Arc<Authenticator>, Role)>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let request = auth.authorize(role, request).await?;
+    Ok(next.run(request).await)
+}
+
+/// Generates a new key: a `ck_`-prefixed id and a 32-byte hex secret.
+pub fn generate_key() -> (String, String) {
+    let mut rng = rand::thread_rng();
+    let mut id = [0u8; 8];
+    let mut secret = [0u8; 32];
+    rng.fill_bytes(&mut id);
+    rng.fill_bytes(&mut secret);
+    (format!("ck_{}", hex::encode(id)), hex::encode(secret))
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authenticator(max_body_bytes: usize) -> Authenticator {
+        Authenticator { enabled: true, replay_window: 60, max_body_bytes, ..Authenticator::disabled() }
+    }
+
+    fn signed_request(body: &'static str) -> Request {
+        Request::builder()
+            .method("POST")
+            .uri("/channel/settle")
+            .header(KEY_ID_HEADER, "unknown")
+            .header(TIMESTAMP_HEADER, now_secs().to_string())
+            .header(SIGNATURE_HEADER, "00")
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_body_is_capped_by_the_configured_limit() {
+        let result = authenticator(8).authorize(Role::ResourceServer, signed_request("0123456789")).await;
+        match result {
+            Err(AppError::BadRequest(message)) => assert!(message.starts_with("invalid request body")),
+            other => panic!("expected a rejected body, got {:?}", other.map(|_| ())),
+        }
+
+        // Within the limit the body is read and the unknown key is what fails.
+        let result = authenticator(64).authorize(Role::ResourceServer, signed_request("0123456789")).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+};
+
+use lru::LruCache;
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::{
+    db::{load_channel, load_channels, load_hot_channels},
+    error::AppError,
+    integrity::LoadVerifier,
+    model::{CacheMetrics, ChannelState},
+};
+
+const LOAD_LOCK_STRIPES: usize = 64;
+
+/// Per-channel handle. Holding the lock serializes updates to that channel only.
+pub type ChannelHandle = Arc<Mutex<ChannelState>>;
+
+/// Bounded LRU of channel handles keyed by normalized channel id (`0x` + lowercase hex).
+///
+/// The cache is write-through: callers persist a state before writing it into a handle,
+/// so an idle entry can be evicted at any time and reloaded from Postgres on the next
+/// access. Entries that are still referenced outside the cache are never evicted, which
+/// guarantees at most one live handle per channel.
+///
+/// With a verifier, rows read by `get_or_load` and `warm_up` are checked before they are
+/// cached. `refresh` and `reload` only pick up writes of this deployment's leader and are
+/// not re-verified.
+pub struct ChannelCache {
+    entries: StdMutex<LruCache<String, ChannelHandle>>,
+    load_locks: Vec<Mutex<()>>,
+    capacity: usize,
+    verifier: Option<LoadVerifier>,
+    counters: CacheCounters,
+}
+
+#[derive(Default)]
+struct CacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    warmed: AtomicU64,
+}
+
+impl ChannelCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: StdMutex::new(LruCache::unbounded()),
+            load_locks: (0..LOAD_LOCK_STRIPES).map(|_| Mutex::new(())).collect(),
+            capacity: capacity.max(1),
+            verifier: None,
+            counters: CacheCounters::default(),
+        }
+    }
+
+    /// Verifies every channel loaded from Postgres before caching it.
+    pub fn with_verifier(mut self, verifier: LoadVerifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Returns the handle for a channel, loading (and verifying) it from Postgres on a miss.
+    pub async fn get_or_load(&self, db: &PgPool, channel_id: &str) -> Result<Option<ChannelHandle>, AppError> {
+        if let Some(handle) = self.get(channel_id) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(handle));
+        }
+
+        // Concurrent misses for the same channel share a stripe, so a stale row can never
+        // be inserted after another loader already created (and updated) the entry.
+        let _load_guard = self.load_lock(channel_id).lock().await;
+        if let Some(handle) = self.get(channel_id) {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(handle));
+        }
+
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        let Some(loaded) = load_channel(db, channel_id).await.transpose() else {
+            return Ok(None);
+        };
+        let channel = match &self.verifier {
+            Some(verifier) => verifier.check(db, channel_id, loaded).await?,
+            None => loaded?,
+        };
+        Ok(Some(self.insert(channel)))
+    }
+
+    /// Inserts an already persisted channel unless it is cached, returning the live handle.
+    pub fn insert(&self, channel: ChannelState) -> ChannelHandle {
+        let key = format!("0x{:x}", channel.channel_id);
+        let mut entries = self.entries.lock().expect("channel cache poisoned");
+        if let Some(existing) = entries.get(&key) {
+            return existing.clone();
+        }
+
+        let handle: ChannelHandle = Arc::new(Mutex::new(channel));
+        entries.put(key, handle.clone());
+        self.evict_idle(&mut entries);
+        handle
+    }
+
+    /// Bulk-loads the most recently signed channels, up to the cache capacity.
+    /// Intended to run before the server starts taking traffic.
+    pub async fn warm_up(&self, db: &PgPool, limit: usize) -> Result<usize, sqlx::Error> {
+        let mut channels = load_hot_channels(db, limit.min(self.capacity)).await?;
+        if let Some(verifier) = &self.verifier {
+            channels = verifier.retain_valid(channels).await;
+        }
+        let loaded = channels.len();
+        for channel in channels {
+            self.insert(channel);
+        }
+        self.counters.warmed.fetch_add(loaded as u64, Ordering::Relaxed);
+        Ok(loaded)
+    }
+
+    /// Reloads every cached channel from Postgres, dropping channels that no longer exist.
+    ///
+    /// Only safe while this process is not writing (standby, or just before promotion),
+    /// since it overwrites handles with whatever is committed.
+    pub async fn refresh(&self, db: &PgPool) -> Result<usize, sqlx::Error> {
+        let cached: Vec<(String, ChannelHandle)> = self
+            .entries
+            .lock()
+            .expect("channel cache poisoned")
+            .iter()
+            .map(|(key, handle)| (key.clone(), handle.clone()))
+            .collect();
+        if cached.is_empty() {
+            return Ok(0);
+        }
+
+        let keys: Vec<String> = cached.iter().map(|(key, _)| key.clone()).collect();
+        let mut fresh: HashMap<String, ChannelState> = load_channels(db, &keys)
+            .await?
+            .into_iter()
+            .map(|channel| (format!("0x{:x}", channel.channel_id), channel))
+            .collect();
+
+        let mut refreshed = 0;
+        for (key, handle) in cached {
+            match fresh.remove(&key) {
+                Some(channel) => {
+                    *handle.lock().await = channel;
+                    refreshed += 1;
+                }
+                None => {
+                    self.entries.lock().expect("channel cache poisoned").pop(&key);
+                }
+            }
+        }
+        Ok(refreshed)
+    }
+
+    /// Reloads one channel from Postgres if it is cached; uncached channels are left to
+    /// the next lazy load. Shares the load stripe so it cannot race a concurrent miss.
+    pub async fn reload(&self, db: &PgPool, channel_id: &str) -> Result<(), sqlx::Error> {
+        let _load_guard = self.load_lock(channel_id).lock().await;
+        let Some(handle) = self.peek(channel_id) else {
+            return Ok(());
+        };
+        match load_channel(db, channel_id).await? {
+            Some(channel) => *handle.lock().await = channel,
+            None => {
+                self.entries.lock().expect("channel cache poisoned").pop(channel_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops a channel that no longer lives in the `channels` table (e.g. after archival).
+    /// Takes the load stripe so a concurrent miss cannot re-insert the old row.
+    pub async fn remove(&self, channel_id: &str) {
+        let _load_guard = self.load_lock(channel_id).lock().await;
+        self.entries.lock().expect("channel cache poisoned").pop(channel_id);
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        let entries = self.entries.lock().expect("channel cache poisoned").len();
+        CacheMetrics {
+            capacity: self.capacity,
+            entries,
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            evictions: self.counters.evictions.load(Ordering::Relaxed),
+            warmed: self.counters.warmed.load(Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, channel_id: &str) -> Option<ChannelHandle> {
+        self.entries
+            .lock()
+            .expect("channel cache poisoned")
+            .get(channel_id)
+            .cloned()
+    }
+
+    fn peek(&self, channel_id: &str) -> Option<ChannelHandle> {
+        self.entries
+            .lock()
+            .expect("channel cache poisoned")
+            .peek(channel_id)
+            .cloned()
+    }
+
+    fn load_lock(&self, channel_id: &str) -> &Mutex<()> {
+        let mut hasher = DefaultHasher::new();
+        channel_id.hash(&mut hasher);
+        &self.load_locks[hasher.finish() as usize % self.load_locks.len()]
+    }
+
+    /// Evicts least recently used entries that nobody else holds until within capacity.
+    /// If every surplus entry is in use the cache temporarily exceeds its bound.
+    fn evict_idle(&self, entries: &mut LruCache<String, ChannelHandle>) {
+        while entries.len() > self.capacity {
+            let idle = entries
+                .iter()
+                .rev()
+                .find(|(_, handle)| Arc::strong_count(handle) == 1)
+                .map(|(key, _)| key.clone());
+            let Some(key) = idle else {
+                break;
+            };
+            entries.pop(&key);
+            self.counters.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use ethers_core::types::Address;
+use ethers_core::types::{Address, U256};
 use std::str::FromStr;
 
 use crate::error::AppError;
@@ -9,6 +9,31 @@ const DEFAULT_CHAIN_ID: u64 = 31337;
 const DEFAULT_MAX_RECIPIENTS: usize = 30;
 const DEFAULT_PORT: u16 = 4001;
 const DEFAULT_SEQUENCER_PRIVATE_KEY: &str = "";
+const DEFAULT_CHANNEL_CACHE_CAPACITY: usize = 10_000;
+const DEFAULT_CHANNEL_CACHE_WARMUP: usize = 0;
+const DEFAULT_WRITE_BATCH_MAX_SIZE: usize = 64;
+const DEFAULT_WRITE_BATCH_MAX_DELAY_MS: u64 = 5;
+const DEFAULT_HA_LOCK_KEY: i64 = 0x4350_4353_4551;
+const DEFAULT_HA_POLL_INTERVAL_MS: u64 = 2_000;
+const DEFAULT_ARCHIVE_INTERVAL_SECS: u64 = 300;
+const DEFAULT_ARCHIVE_AFTER_SECS: u64 = 86_400;
+const DEFAULT_ARCHIVE_RETENTION_DAYS: u64 = 0;
+const DEFAULT_FINALIZE_REQUEST_COOLDOWN_SECS: u64 = 3_600;
+const DEFAULT_FINALIZE_BATCH_INTERVAL_SECS: u64 = 30;
+const DEFAULT_FINALIZE_BATCH_MAX_SIZE: usize = 20;
+const DEFAULT_AUTH_REPLAY_WINDOW_SECS: u64 = 300;
+const DEFAULT_AUTH_KEY_REFRESH_SECS: u64 = 30;
+const DEFAULT_SESSION_TTL_SECS: u64 = 900;
+const DEFAULT_RATE_LIMIT_IP_PER_SEC: f64 = 100.0;
+const DEFAULT_RATE_LIMIT_IP_BURST: f64 = 200.0;
+const DEFAULT_RATE_LIMIT_KEY_PER_SEC: f64 = 50.0;
+const DEFAULT_RATE_LIMIT_KEY_BURST: f64 = 100.0;
+const DEFAULT_RATE_LIMIT_OWNER_PER_SEC: f64 = 10.0;
+const DEFAULT_RATE_LIMIT_OWNER_BURST: f64 = 20.0;
+const DEFAULT_SIGNATURE_FAILURE_LIMIT: u32 = 10;
+const DEFAULT_SIGNATURE_FAILURE_WINDOW_SECS: u64 = 60;
+const DEFAULT_BAN_SECS: u64 = 600;
+const DEFAULT_MAX_BODY_BYTES: usize = 256 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -19,6 +44,81 @@ pub struct Config {
     pub max_recipients: usize,
     pub sequencer_private_key: String,
     pub port: u16,
+    pub channel_cache_capacity: usize,
+    pub channel_cache_warmup: usize,
+    pub write_batch_enabled: bool,
+    pub write_batch_max_size: usize,
+    pub write_batch_max_delay_ms: u64,
+    pub ha_enabled: bool,
+    pub ha_node_id: String,
+    pub ha_lock_key: i64,
+    pub ha_poll_interval_ms: u64,
+    pub read_replica: bool,
+    /// Verify every stored channel before serving and quarantine the ones that fail.
+    pub verify_on_startup: bool,
+    pub archive_enabled: bool,
+    pub archive_interval_secs: u64,
+    /// How long a closed or expired channel stays live before it is archived.
+    pub archive_after_secs: u64,
+    /// How long archived channels are kept; `0` keeps them forever.
+    pub archive_retention_days: u64,
+    /// Smallest balance a recipient must be owed to request a finalize.
+    pub finalize_request_min_owed: U256,
+    /// How long a recipient must wait between finalize requests for the same channel.
+    pub finalize_request_cooldown_secs: u64,
+    pub finalize_batch_interval_secs: u64,
+    /// Most channels closed by one `finalCloseBySequencerBatch` transaction.
+    pub finalize_batch_max_size: usize,
+    /// Fee destination advertised in generated x402 payment requirements.
+    pub fee_destination_address: Option<Address>,
+    /// Require API keys on every route that is not public (see `auth`).
+    pub auth_enabled: bool,
+    /// How far an HMAC-signed request's timestamp may be from the server clock.
+    pub auth_replay_window_secs: u64,
+    /// How often API keys are reloaded from Postgres, which bounds how long a revoked key works.
+    pub auth_key_refresh_secs: u64,
+    /// Lifetime of wallet session tokens issued by SIWE login.
+    pub session_ttl_secs: u64,
+    /// `domain` and `URI` of SIWE messages; wallets show them to the user.
+    pub siwe_domain: String,
+    pub siwe_uri: String,
+    /// Token-bucket limits (see `ratelimit`); a rate of `0` turns that limit off.
+    pub rate_limit_enabled: bool,
+    pub rate_limit_ip: RateLimit,
+    pub rate_limit_key: RateLimit,
+    pub rate_limit_owner: RateLimit,
+    /// Trusted proxies in front of the sequencer; the client is the `X-Forwarded-For` entry
+    /// this many hops from the right. `0` ignores the header.
+    pub rate_limit_trusted_proxy_hops: usize,
+    /// Signature failures within `signature_failure_window_secs` that get a client banned.
+    pub signature_failure_limit: u32,
+    pub signature_failure_window_secs: u64,
+    pub ban_secs: u64,
+    /// Largest request body accepted by any route.
+    pub max_body_bytes: usize,
+}
+
+/// Sustained `per_sec` requests with bursts of up to `burst`.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub per_sec: f64,
+    pub burst: f64,
+}
+
+impl RateLimit {
+    fn from_env(prefix: &str, per_sec: f64, burst: f64) -> Self {
+        let read = |suffix: &str, default: f64| {
+            std::env::var(format!("{prefix}_{suffix}"))
+                .ok()
+                .and_then(|v| v.parse::<f64>().ok())
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or(default)
+        };
+        Self {
+            per_sec: read("PER_SEC", per_sec),
+            burst: read("BURST", burst),
+        }
+    }
 }
 
 impl Config {
@@ -43,10 +143,146 @@ impl Config {
             .ok()
             .and_then(|v| v.parse::<u16>().ok())
             .unwrap_or(DEFAULT_PORT);
+        let channel_cache_capacity = std::env::var("CHANNEL_CACHE_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CHANNEL_CACHE_CAPACITY);
+        let channel_cache_warmup = std::env::var("CHANNEL_CACHE_WARMUP")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_CHANNEL_CACHE_WARMUP);
+        let write_batch_enabled = std::env::var("WRITE_BATCH_ENABLED")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(false);
+        let write_batch_max_size = std::env::var("WRITE_BATCH_MAX_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_WRITE_BATCH_MAX_SIZE);
+        let write_batch_max_delay_ms = std::env::var("WRITE_BATCH_MAX_DELAY_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_WRITE_BATCH_MAX_DELAY_MS);
+        let ha_enabled = std::env::var("HA_ENABLED")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(false);
+        let ha_node_id = std::env::var("HA_NODE_ID")
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .unwrap_or_else(|_| format!("sequencer-{}", std::process::id()));
+        let ha_lock_key = std::env::var("HA_LOCK_KEY")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_HA_LOCK_KEY);
+        let ha_poll_interval_ms = std::env::var("HA_POLL_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_HA_POLL_INTERVAL_MS);
+        let read_replica = match std::env::var("SEQUENCER_MODE").as_deref() {
+            Ok("replica") => true,
+            Ok("primary") | Err(_) => false,
+            Ok(other) => return Err(AppError::bad_request(format!("unknown SEQUENCER_MODE: {other}"))),
+        };
+
+        let verify_on_startup = std::env::var("VERIFY_ON_STARTUP")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(true);
+        let archive_enabled = std::env::var("ARCHIVE_ENABLED")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(false);
+        let archive_interval_secs = std::env::var("ARCHIVE_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_ARCHIVE_INTERVAL_SECS);
+        let archive_after_secs = std::env::var("ARCHIVE_AFTER_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_ARCHIVE_AFTER_SECS);
+        let archive_retention_days = std::env::var("ARCHIVE_RETENTION_DAYS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_ARCHIVE_RETENTION_DAYS);
+        let finalize_request_min_owed = match std::env::var("FINALIZE_REQUEST_MIN_OWED") {
+            Ok(value) => U256::from_dec_str(&value)
+                .map_err(|_| AppError::bad_request(format!("invalid FINALIZE_REQUEST_MIN_OWED: {value}")))?,
+            Err(_) => U256::zero(),
+        };
+        let finalize_request_cooldown_secs = std::env::var("FINALIZE_REQUEST_COOLDOWN_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_FINALIZE_REQUEST_COOLDOWN_SECS);
+        let finalize_batch_interval_secs = std::env::var("FINALIZE_BATCH_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_FINALIZE_BATCH_INTERVAL_SECS);
+        let finalize_batch_max_size = std::env::var("FINALIZE_BATCH_MAX_SIZE")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_FINALIZE_BATCH_MAX_SIZE);
+        let fee_destination_address = match std::env::var("FEE_DESTINATION_ADDRESS") {
+            Ok(value) => Some(
+                Address::from_str(&value)
+                    .map_err(|_| AppError::bad_request(format!("invalid FEE_DESTINATION_ADDRESS: {value}")))?,
+            ),
+            Err(_) => None,
+        };
+        let auth_enabled = std::env::var("AUTH_ENABLED")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(false);
+        let auth_replay_window_secs = std::env::var("AUTH_REPLAY_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_AUTH_REPLAY_WINDOW_SECS);
+        let auth_key_refresh_secs = std::env::var("AUTH_KEY_REFRESH_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_AUTH_KEY_REFRESH_SECS);
+        let session_ttl_secs = std::env::var("SESSION_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS);
+        let siwe_domain = std::env::var("SIWE_DOMAIN").unwrap_or_else(|_| format!("localhost:{port}"));
+        let siwe_uri = std::env::var("SIWE_URI").unwrap_or_else(|_| format!("http://{siwe_domain}"));
+        let rate_limit_enabled = std::env::var("RATE_LIMIT_ENABLED")
+            .ok()
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(true);
+        let rate_limit_ip =
+            RateLimit::from_env("RATE_LIMIT_IP", DEFAULT_RATE_LIMIT_IP_PER_SEC, DEFAULT_RATE_LIMIT_IP_BURST);
+        let rate_limit_key =
+            RateLimit::from_env("RATE_LIMIT_KEY", DEFAULT_RATE_LIMIT_KEY_PER_SEC, DEFAULT_RATE_LIMIT_KEY_BURST);
+        let rate_limit_owner =
+            RateLimit::from_env("RATE_LIMIT_OWNER", DEFAULT_RATE_LIMIT_OWNER_PER_SEC, DEFAULT_RATE_LIMIT_OWNER_BURST);
+        let rate_limit_trusted_proxy_hops = std::env::var("RATE_LIMIT_TRUSTED_PROXY_HOPS")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        let signature_failure_limit = std::env::var("SIGNATURE_FAILURE_LIMIT")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_SIGNATURE_FAILURE_LIMIT);
+        let signature_failure_window_secs = std::env::var("SIGNATURE_FAILURE_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_SIGNATURE_FAILURE_WINDOW_SECS);
+        let ban_secs = std::env::var("BAN_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_BAN_SECS);
+        let max_body_bytes = std::env::var("MAX_BODY_BYTES")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_MAX_BODY_BYTES);
 
         if channel_manager == Address::zero() {
             return Err(AppError::bad_request("CHANNEL_MANAGER_ADDRESS resolved to zero address"));
         }
+        if read_replica && ha_enabled {
+            return Err(AppError::bad_request("SEQUENCER_MODE=replica cannot be combined with HA_ENABLED"));
+        }
         if sequencer_private_key.is_empty() {
             return Err(AppError::bad_request("SEQUENCER_PRIVATE_KEY is not set"));
         }
@@ -59,6 +295,41 @@ impl Config {
             max_recipients,
             sequencer_private_key,
             port,
+            channel_cache_capacity,
+            channel_cache_warmup,
+            write_batch_enabled,
+            write_batch_max_size,
+            write_batch_max_delay_ms,
+            ha_enabled,
+            ha_node_id,
+            ha_lock_key,
+            ha_poll_interval_ms,
+            read_replica,
+            verify_on_startup,
+            archive_enabled,
+            archive_interval_secs,
+            archive_after_secs,
+            archive_retention_days,
+            finalize_request_min_owed,
+            finalize_request_cooldown_secs,
+            finalize_batch_interval_secs,
+            finalize_batch_max_size,
+            fee_destination_address,
+            auth_enabled,
+            auth_replay_window_secs,
+            auth_key_refresh_secs,
+            session_ttl_secs,
+            siwe_domain,
+            siwe_uri,
+            rate_limit_enabled,
+            rate_limit_ip,
+            rate_limit_key,
+            rate_limit_owner,
+            rate_limit_trusted_proxy_hops,
+            signature_failure_limit,
+            signature_failure_window_secs,
+            ban_secs,
+            max_body_bytes,
         })
     }
 }
@@ -4,11 +4,12 @@ use ethers_core::{
     utils::keccak256,
 };
 use ethers_signers::LocalWallet;
+use serde_json::json;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::error::AppError;
-use crate::model::RecipientBalance;
+use crate::model::{RecipientBalance, TypedDataDomain};
 
 const DOMAIN_NAME: &str = "X402CheddrPaymentChannel";
 const DOMAIN_VERSION: &str = "1";
@@ -25,12 +26,15 @@ pub fn parse_u256(input: &str) -> Result<U256, AppError> {
     U256::from_dec_str(input).map_err(|_| AppError::bad_request(format!("invalid uint256: {input}")))
 }
 
+/// How far ahead of the server clock a signature timestamp may be (the contract allows 15 minutes).
+pub const TIMESTAMP_MAX_FUTURE_SECS: u64 = 15 * 60;
+
 pub fn validate_timestamp(timestamp: u64, expiry_ts: u64) -> Result<(), AppError> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or(Duration::from_secs(0))
         .as_secs();
-    let max_future = now + 15 * 60;
+    let max_future = now + TIMESTAMP_MAX_FUTURE_SECS;
     if timestamp > max_future {
         return Err(AppError::bad_request("timestamp is too far in the future"));
     }
@@ -60,7 +64,7 @@ pub fn recover_signature(
     let sig = Signature::from_str(signature)
         .map_err(|e| AppError::bad_request(format!("invalid signature: {e}")))?;
     sig.recover(digest)
-        .map_err(|e| AppError::bad_request(format!("signature recovery failed: {e}")))
+        .map_err(|e| AppError::invalid_signature(format!("signature recovery failed: {e}")))
 }
 
 pub fn sign_update(
@@ -86,7 +90,8 @@ pub fn sign_update(
     Ok(signature.to_string())
 }
 
-fn channel_update_digest(
+/// EIP-712 digest of a `ChannelData` state, as computed by the contract's `_hashTypedDataV4`.
+pub fn channel_update_digest(
     channel_id: H256,
     sequence_number: u64,
     timestamp: u64,
@@ -111,8 +116,157 @@ fn channel_update_digest(
     ]);
     let struct_hash = H256::from(keccak256(struct_encoded));
 
-    let domain_separator = domain_separator(chain_id, verifying_contract);
+    typed_data_digest(domain_separator(chain_id, verifying_contract), struct_hash)
+}
+
+/// `eth_signTypedData_v4` payload of a `ChannelData` update; signing it yields the
+/// signature `recover_signature` expects for the same arguments.
+pub fn channel_update_typed_data(
+    channel_id: H256,
+    sequence_number: u64,
+    timestamp: u64,
+    recipients: &[RecipientBalance],
+    chain_id: u64,
+    verifying_contract: Address,
+) -> serde_json::Value {
+    json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            "ChannelData": [
+                { "name": "channelId", "type": "bytes32" },
+                { "name": "sequenceNumber", "type": "uint256" },
+                { "name": "timestamp", "type": "uint256" },
+                { "name": "recipients", "type": "address[]" },
+                { "name": "amounts", "type": "uint256[]" },
+            ],
+        },
+        "primaryType": "ChannelData",
+        "domain": typed_data_domain(chain_id, verifying_contract),
+        "message": {
+            "channelId": format!("0x{:x}", channel_id),
+            "sequenceNumber": sequence_number,
+            "timestamp": timestamp,
+            "recipients": recipients.iter().map(|r| format!("0x{:x}", r.recipient_address)).collect::<Vec<_>>(),
+            "amounts": recipients.iter().map(|r| r.balance.to_string()).collect::<Vec<_>>(),
+        },
+    })
+}
+
+/// Recovers the signer of a recipient's `FinalizeRequest`, signed as EIP-712 typed data
+/// under the channel manager's domain.
+pub fn recover_finalize_request(
+    channel_id: H256,
+    recipient: Address,
+    timestamp: u64,
+    reason: &str,
+    chain_id: u64,
+    verifying_contract: Address,
+    signature: &str,
+) -> Result<Address, AppError> {
+    let type_hash = keccak256(b"FinalizeRequest(bytes32 channelId,address recipient,uint256 timestamp,string reason)");
+    let struct_encoded = encode(&[
+        Token::FixedBytes(type_hash.to_vec()),
+        Token::FixedBytes(channel_id.as_bytes().to_vec()),
+        Token::Address(recipient),
+        Token::Uint(U256::from(timestamp)),
+        Token::FixedBytes(keccak256(reason.as_bytes()).to_vec()),
+    ]);
+    let struct_hash = H256::from(keccak256(struct_encoded));
+    recover_digest(typed_data_digest(domain_separator(chain_id, verifying_contract), struct_hash), signature)
+}
+
+/// Recovers the signer of an owner's `CloseRequest`, signed as EIP-712 typed data under the
+/// channel manager's domain. The owner agrees to close at exactly `sequence_number`.
+pub fn recover_close_request(
+    channel_id: H256,
+    sequence_number: u64,
+    timestamp: u64,
+    chain_id: u64,
+    verifying_contract: Address,
+    signature: &str,
+) -> Result<Address, AppError> {
+    let type_hash = keccak256(b"CloseRequest(bytes32 channelId,uint256 sequenceNumber,uint256 timestamp)");
+    let struct_encoded = encode(&[
+        Token::FixedBytes(type_hash.to_vec()),
+        Token::FixedBytes(channel_id.as_bytes().to_vec()),
+        Token::Uint(U256::from(sequence_number)),
+        Token::Uint(U256::from(timestamp)),
+    ]);
+    let struct_hash = H256::from(keccak256(struct_encoded));
+    recover_digest(typed_data_digest(domain_separator(chain_id, verifying_contract), struct_hash), signature)
+}
+
+/// `eth_signTypedData_v4` payload of a `ReceiverBinding`, signed by a receiver to bind
+/// itself to the API key that asked for the challenge.
+pub fn receiver_binding_typed_data(
+    receiver: Address,
+    key_id: &str,
+    nonce: H256,
+    expires_at: u64,
+    chain_id: u64,
+    verifying_contract: Address,
+) -> serde_json::Value {
+    json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+                { "name": "chainId", "type": "uint256" },
+                { "name": "verifyingContract", "type": "address" },
+            ],
+            "ReceiverBinding": [
+                { "name": "receiver", "type": "address" },
+                { "name": "keyId", "type": "string" },
+                { "name": "nonce", "type": "bytes32" },
+                { "name": "expiresAt", "type": "uint256" },
+            ],
+        },
+        "primaryType": "ReceiverBinding",
+        "domain": typed_data_domain(chain_id, verifying_contract),
+        "message": {
+            "receiver": format!("0x{:x}", receiver),
+            "keyId": key_id,
+            "nonce": format!("0x{:x}", nonce),
+            "expiresAt": expires_at,
+        },
+    })
+}
 
+/// Recovers the signer of a `ReceiverBinding` (see `receiver_binding_typed_data`).
+pub fn recover_receiver_binding(
+    receiver: Address,
+    key_id: &str,
+    nonce: H256,
+    expires_at: u64,
+    chain_id: u64,
+    verifying_contract: Address,
+    signature: &str,
+) -> Result<Address, AppError> {
+    let type_hash = keccak256(b"ReceiverBinding(address receiver,string keyId,bytes32 nonce,uint256 expiresAt)");
+    let struct_encoded = encode(&[
+        Token::FixedBytes(type_hash.to_vec()),
+        Token::Address(receiver),
+        Token::FixedBytes(keccak256(key_id.as_bytes()).to_vec()),
+        Token::FixedBytes(nonce.as_bytes().to_vec()),
+        Token::Uint(U256::from(expires_at)),
+    ]);
+    let struct_hash = H256::from(keccak256(struct_encoded));
+    recover_digest(typed_data_digest(domain_separator(chain_id, verifying_contract), struct_hash), signature)
+}
+
+fn recover_digest(digest: H256, signature: &str) -> Result<Address, AppError> {
+    let sig = Signature::from_str(signature)
+        .map_err(|e| AppError::bad_request(format!("invalid signature: {e}")))?;
+    sig.recover(digest)
+        .map_err(|e| AppError::invalid_signature(format!("signature recovery failed: {e}")))
+}
+
+fn typed_data_digest(domain_separator: H256, struct_hash: H256) -> H256 {
     let mut digest_input = Vec::with_capacity(2 + 32 + 32);
     digest_input.extend_from_slice(&[0x19, 0x01]);
     digest_input.extend_from_slice(domain_separator.as_bytes());
@@ -120,7 +274,45 @@ fn channel_update_digest(
     H256::from(keccak256(digest_input))
 }
 
-fn domain_separator(chain_id: u64, verifying_contract: Address) -> H256 {
+/// ABI-encoded call to `publishIntermediateChannelState` for a co-signed state.
+pub fn encode_publish_intermediate_state(
+    channel_id: H256,
+    sequence_number: u64,
+    timestamp: u64,
+    recipients: &[RecipientBalance],
+    user_signature: Vec<u8>,
+    sequencer_signature: Vec<u8>,
+) -> Vec<u8> {
+    let selector = &keccak256(
+        b"publishIntermediateChannelState(bytes32,uint256,uint256,address[],uint256[],bytes,bytes)",
+    )[..4];
+    let arguments = encode(&[
+        Token::FixedBytes(channel_id.as_bytes().to_vec()),
+        Token::Uint(U256::from(sequence_number)),
+        Token::Uint(U256::from(timestamp)),
+        Token::Array(recipients.iter().map(|r| Token::Address(r.recipient_address)).collect()),
+        Token::Array(recipients.iter().map(|r| Token::Uint(r.balance)).collect()),
+        Token::Bytes(user_signature),
+        Token::Bytes(sequencer_signature),
+    ]);
+
+    let mut calldata = Vec::with_capacity(selector.len() + arguments.len());
+    calldata.extend_from_slice(selector);
+    calldata.extend_from_slice(&arguments);
+    calldata
+}
+
+/// The EIP-712 domain `domain_separator` hashes, for clients that build typed data.
+pub fn typed_data_domain(chain_id: u64, verifying_contract: Address) -> TypedDataDomain {
+    TypedDataDomain {
+        name: DOMAIN_NAME.to_string(),
+        version: DOMAIN_VERSION.to_string(),
+        chain_id,
+        verifying_contract: format!("0x{:x}", verifying_contract),
+    }
+}
+
+pub fn domain_separator(chain_id: u64, verifying_contract: Address) -> H256 {
     let domain_type_hash = keccak256(
         b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)",
     );
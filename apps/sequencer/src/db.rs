@@ -1,8 +1,13 @@
-use sqlx::{PgPool, Row};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Postgres, QueryBuilder, Row};
 use std::collections::HashMap;
+use thiserror::Error;
 
 use crate::crypto::{parse_address, parse_h256, parse_u256};
-use crate::model::{ChannelState, RecipientBalance};
+use crate::error::AppError;
+use crate::model::{
+    ChannelRecord, ChannelSort, ChannelState, ChannelStatus, ChannelSummary, FinalizeRequestStatus,
+    RecipientBalance, RecipientChannelBalance, RecipientRecord,
+};
 
 pub async fn init_db(db: &PgPool) -> Result<(), sqlx::Error> {
     sqlx::query(
@@ -26,6 +31,51 @@ pub async fn init_db(db: &PgPool) -> Result<(), sqlx::Error> {
     .execute(db)
     .await?;
 
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS close_tx_hash TEXT")
+        .execute(db)
+        .await?;
+
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS closed_at BIGINT")
+        .execute(db)
+        .await?;
+
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS quarantine_reason TEXT")
+        .execute(db)
+        .await?;
+
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS quarantined_at BIGINT")
+        .execute(db)
+        .await?;
+
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'open'")
+        .execute(db)
+        .await?;
+
+    // Rows written before the status column existed.
+    sqlx::query(
+        "UPDATE channels SET status = CASE WHEN quarantine_reason IS NOT NULL THEN 'quarantined' ELSE 'closed' END \
+         WHERE status = 'open' AND (quarantine_reason IS NOT NULL OR closed_at IS NOT NULL)",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query("ALTER TABLE channels ADD COLUMN IF NOT EXISTS updated_at BIGINT NOT NULL DEFAULT 0")
+        .execute(db)
+        .await?;
+
+    // Rows written before `updated_at` existed, or imported from a snapshot.
+    sqlx::query("UPDATE channels SET updated_at = signature_timestamp WHERE updated_at = 0")
+        .execute(db)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS channels_owner_idx ON channels (owner)")
+        .execute(db)
+        .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS channels_updated_at_idx ON channels (updated_at)")
+        .execute(db)
+        .await?;
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS recipients (\
             channel_id TEXT NOT NULL,\
@@ -39,68 +89,1018 @@ pub async fn init_db(db: &PgPool) -> Result<(), sqlx::Error> {
     .execute(db)
     .await?;
 
+    sqlx::query("CREATE INDEX IF NOT EXISTS recipients_address_idx ON recipients (recipient_address)")
+        .execute(db)
+        .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS archived_channels (\
+            channel_id TEXT PRIMARY KEY,\
+            owner TEXT NOT NULL,\
+            balance TEXT NOT NULL,\
+            expiry_ts BIGINT NOT NULL,\
+            sequence_number BIGINT NOT NULL,\
+            user_signature TEXT NOT NULL,\
+            sequencer_signature TEXT NOT NULL,\
+            signature_timestamp BIGINT NOT NULL,\
+            status TEXT NOT NULL DEFAULT 'open',\
+            close_tx_hash TEXT,\
+            closed_at BIGINT,\
+            archived_at BIGINT NOT NULL\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query("ALTER TABLE archived_channels ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'open'")
+        .execute(db)
+        .await?;
+
+    sqlx::query(
+        "UPDATE archived_channels SET status = 'closed' WHERE status = 'open' AND closed_at IS NOT NULL",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS archived_recipients (\
+            channel_id TEXT NOT NULL,\
+            recipient_address TEXT NOT NULL,\
+            balance TEXT NOT NULL,\
+            position INT NOT NULL,\
+            PRIMARY KEY (channel_id, recipient_address),\
+            FOREIGN KEY (channel_id) REFERENCES archived_channels(channel_id) ON DELETE CASCADE\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS finalize_requests (\
+            id BIGSERIAL PRIMARY KEY,\
+            channel_id TEXT NOT NULL,\
+            recipient TEXT NOT NULL,\
+            reason TEXT NOT NULL DEFAULT '',\
+            requested_at BIGINT NOT NULL,\
+            status TEXT NOT NULL DEFAULT 'pending',\
+            tx_hash TEXT,\
+            error TEXT,\
+            processed_at BIGINT\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query("ALTER TABLE finalize_requests ADD COLUMN IF NOT EXISTS retry_alone BOOLEAN NOT NULL DEFAULT false")
+        .execute(db)
+        .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS finalize_requests_pending_idx ON finalize_requests (requested_at) \
+         WHERE status = 'pending'",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS finalize_requests_recipient_idx ON finalize_requests (channel_id, recipient)",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS close_requests (\
+            channel_id TEXT NOT NULL,\
+            sequence_number BIGINT NOT NULL,\
+            signature TEXT NOT NULL,\
+            timestamp BIGINT NOT NULL,\
+            accepted_at BIGINT NOT NULL,\
+            PRIMARY KEY (channel_id, sequence_number)\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS api_keys (\
+            key_id TEXT PRIMARY KEY,\
+            secret TEXT NOT NULL,\
+            role TEXT NOT NULL,\
+            label TEXT NOT NULL DEFAULT '',\
+            created_at BIGINT NOT NULL,\
+            revoked_at BIGINT\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS receiver_challenges (\
+            nonce TEXT PRIMARY KEY,\
+            key_id TEXT NOT NULL,\
+            receiver TEXT NOT NULL,\
+            expires_at BIGINT NOT NULL\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS receiver_bindings (\
+            key_id TEXT NOT NULL,\
+            receiver TEXT NOT NULL,\
+            bound_at BIGINT NOT NULL,\
+            PRIMARY KEY (key_id, receiver)\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS siwe_nonces (\
+            nonce TEXT PRIMARY KEY,\
+            address TEXT NOT NULL,\
+            message TEXT NOT NULL,\
+            expires_at BIGINT NOT NULL\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS sequencer_leader (\
+            id SMALLINT PRIMARY KEY CHECK (id = 1),\
+            epoch BIGINT NOT NULL,\
+            holder TEXT NOT NULL,\
+            acquired_at TIMESTAMPTZ NOT NULL DEFAULT now()\
+        )",
+    )
+    .execute(db)
+    .await?;
+
+    sqlx::query("INSERT INTO sequencer_leader (id, epoch, holder) VALUES (1, 0, '') ON CONFLICT (id) DO NOTHING")
+        .execute(db)
+        .await?;
+
     Ok(())
 }
 
-pub async fn load_state(db: &PgPool) -> Result<HashMap<String, ChannelState>, sqlx::Error> {
-    let mut map = HashMap::new();
-    let rows = sqlx::query(
-        "SELECT channel_id, owner, balance, expiry_ts, sequence_number, user_signature, sequencer_signature, signature_timestamp FROM channels",
+/// `LISTEN/NOTIFY` channel carrying one message per committed channel write.
+pub const CHANNEL_UPDATES: &str = "channel_updates";
+
+const CHANNEL_COLUMNS: &str = "channel_id, owner, balance, expiry_ts, sequence_number, user_signature, \
+     sequencer_signature, signature_timestamp, status, close_tx_hash, closed_at";
+
+/// Rows flagged by the integrity scan stay in `channels` for inspection but are never served.
+const LIVE: &str = "status <> 'quarantined'";
+
+/// Stored status with expiry applied, matching `ChannelState::effective_status`.
+const EFFECTIVE_STATUS: &str =
+    "CASE WHEN status = 'open' AND expiry_ts < extract(epoch FROM now())::bigint THEN 'expired' ELSE status END";
+
+pub async fn load_channel(db: &PgPool, channel_id: &str) -> Result<Option<ChannelState>, sqlx::Error> {
+    load_channel_with(
+        db,
+        &format!("SELECT {CHANNEL_COLUMNS} FROM channels WHERE channel_id = $1 AND {LIVE}"),
+        "SELECT recipient_address, balance, position FROM recipients WHERE channel_id = $1 ORDER BY position",
+        channel_id,
+    )
+    .await
+}
+
+/// Loads a channel that the archiver has moved out of the live tables.
+pub async fn load_archived_channel(db: &PgPool, channel_id: &str) -> Result<Option<ChannelState>, sqlx::Error> {
+    load_channel_with(
+        db,
+        &format!("SELECT {CHANNEL_COLUMNS} FROM archived_channels WHERE channel_id = $1"),
+        "SELECT recipient_address, balance, position FROM archived_recipients WHERE channel_id = $1 ORDER BY position",
+        channel_id,
     )
+    .await
+}
+
+async fn load_channel_with(
+    db: &PgPool,
+    channel_query: &str,
+    recipients_query: &str,
+    channel_id: &str,
+) -> Result<Option<ChannelState>, sqlx::Error> {
+    let row = sqlx::query(channel_query)
+        .bind(channel_id)
+        .fetch_optional(db)
+        .await?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let recipients_rows = sqlx::query(recipients_query)
+        .bind(channel_id)
+        .fetch_all(db)
+        .await?;
+
+    let mut recipients = Vec::with_capacity(recipients_rows.len());
+    for recipient_row in recipients_rows {
+        recipients.push(recipient_from_row(&recipient_row)?);
+    }
+
+    Ok(Some(channel_from_row(&row, recipients)?))
+}
+
+/// Loads the `limit` most recently signed channels with two queries, for cache warm-up.
+pub async fn load_hot_channels(db: &PgPool, limit: usize) -> Result<Vec<ChannelState>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {CHANNEL_COLUMNS} FROM channels WHERE {LIVE} ORDER BY signature_timestamp DESC LIMIT $1"
+    ))
+    .bind(limit as i64)
     .fetch_all(db)
     .await?;
+    with_recipients(db, rows, "recipients").await
+}
 
-    for row in rows {
-        let channel_id_str: String = row.try_get("channel_id")?;
-        let owner_str: String = row.try_get("owner")?;
-        let balance_str: String = row.try_get("balance")?;
-        let expiry_ts: i64 = row.try_get("expiry_ts")?;
-        let sequence_number: i64 = row.try_get("sequence_number")?;
-        let user_signature: String = row.try_get("user_signature")?;
-        let sequencer_signature: String = row.try_get("sequencer_signature")?;
-        let signature_timestamp: i64 = row.try_get("signature_timestamp")?;
-
-        let recipients_rows = sqlx::query(
-            "SELECT recipient_address, balance, position FROM recipients WHERE channel_id = $1 ORDER BY position",
-        )
-        .bind(&channel_id_str)
+/// Loads the given channels with two queries; ids that are not stored are skipped.
+pub async fn load_channels(db: &PgPool, channel_ids: &[String]) -> Result<Vec<ChannelState>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {CHANNEL_COLUMNS} FROM channels WHERE channel_id = ANY($1) AND {LIVE}"
+    ))
+    .bind(channel_ids)
+    .fetch_all(db)
+    .await?;
+    with_recipients(db, rows, "recipients").await
+}
+
+/// Loads every live channel stored for `owner` (normalized address).
+pub async fn load_channels_by_owner(db: &PgPool, owner: &str) -> Result<Vec<ChannelState>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {CHANNEL_COLUMNS} FROM channels WHERE owner = $1 AND {LIVE} ORDER BY channel_id"
+    ))
+    .bind(owner)
+    .fetch_all(db)
+    .await?;
+    with_recipients(db, rows, "recipients").await
+}
+
+/// Loads the given channels from the archive tables; ids that are not archived are skipped.
+pub async fn load_archived_channels(db: &PgPool, channel_ids: &[String]) -> Result<Vec<ChannelState>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {CHANNEL_COLUMNS} FROM archived_channels WHERE channel_id = ANY($1)"
+    ))
+    .bind(channel_ids)
+    .fetch_all(db)
+    .await?;
+    with_recipients(db, rows, "archived_recipients").await
+}
+
+/// Loads one page of live channels with ids after `after`, in id order, for integrity
+/// scans. A row that fails to parse is returned with its error instead of failing the page.
+pub async fn scan_channels(
+    db: &PgPool,
+    after: &str,
+    limit: usize,
+) -> Result<Vec<(String, Result<ChannelState, sqlx::Error>)>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT {CHANNEL_COLUMNS} FROM channels WHERE channel_id > $1 AND {LIVE} ORDER BY channel_id LIMIT $2"
+    ))
+    .bind(after)
+    .bind(limit as i64)
+    .fetch_all(db)
+    .await?;
+    parse_with_recipients(db, rows, "recipients").await
+}
+
+/// Filters for [`search_channels`]. Addresses are normalized (`0x` + lowercase hex) and
+/// `min_remaining` is a decimal uint256.
+#[derive(Debug, Default)]
+pub struct ChannelFilter {
+    pub owner: Option<String>,
+    pub recipient: Option<String>,
+    pub status: Option<ChannelStatus>,
+    pub expires_after: Option<u64>,
+    pub expires_before: Option<u64>,
+    pub min_remaining: Option<String>,
+    pub updated_since: Option<u64>,
+}
+
+/// Lists live channels matching `filter`, ordered by `sort` and then channel id. `after` is
+/// the `(sort key, channel id)` of the last row of the previous page (keyset pagination).
+pub async fn search_channels(
+    db: &PgPool,
+    filter: &ChannelFilter,
+    sort: ChannelSort,
+    descending: bool,
+    after: Option<(&str, &str)>,
+    limit: usize,
+) -> Result<Vec<ChannelSummary>, sqlx::Error> {
+    let mut query = QueryBuilder::<Postgres>::new(format!(
+        "SELECT s.channel_id, s.owner, s.balance, s.remaining::text AS remaining_capacity, s.expiry_ts, \
+            s.status, s.sequence_number, s.recipient_count, s.updated_at \
+         FROM (\
+            SELECT c.channel_id, c.owner, c.balance, c.expiry_ts, c.sequence_number, c.updated_at, \
+                {EFFECTIVE_STATUS} AS status, a.recipient_count, c.balance::numeric - a.allocated AS remaining \
+            FROM channels c \
+            CROSS JOIN LATERAL (\
+                SELECT count(*) AS recipient_count, COALESCE(sum(r.balance::numeric), 0) AS allocated \
+                FROM recipients r WHERE r.channel_id = c.channel_id\
+            ) a \
+            WHERE {LIVE}\
+         ) s WHERE true"
+    ));
+    if let Some(owner) = &filter.owner {
+        query.push(" AND s.owner = ").push_bind(owner);
+    }
+    if let Some(recipient) = &filter.recipient {
+        query
+            .push(" AND EXISTS (SELECT 1 FROM recipients r WHERE r.channel_id = s.channel_id AND r.recipient_address = ")
+            .push_bind(recipient)
+            .push(")");
+    }
+    if let Some(status) = filter.status {
+        query.push(" AND s.status = ").push_bind(status.as_str());
+    }
+    if let Some(expires_after) = filter.expires_after {
+        query.push(" AND s.expiry_ts >= ").push_bind(expires_after as i64);
+    }
+    if let Some(expires_before) = filter.expires_before {
+        query.push(" AND s.expiry_ts <= ").push_bind(expires_before as i64);
+    }
+    if let Some(min_remaining) = &filter.min_remaining {
+        query.push(" AND s.remaining >= ").push_bind(min_remaining).push("::numeric");
+    }
+    if let Some(updated_since) = filter.updated_since {
+        query.push(" AND s.updated_at >= ").push_bind(updated_since as i64);
+    }
+
+    let (column, cast) = match sort {
+        ChannelSort::ChannelId => ("s.channel_id", "text"),
+        ChannelSort::Expiry => ("s.expiry_ts", "bigint"),
+        ChannelSort::UpdatedAt => ("s.updated_at", "bigint"),
+        ChannelSort::Remaining => ("s.remaining", "numeric"),
+    };
+    let direction = if descending { "DESC" } else { "ASC" };
+    if let Some((key, channel_id)) = after {
+        query
+            .push(format!(" AND ({column}, s.channel_id) {} (", if descending { "<" } else { ">" }))
+            .push_bind(key)
+            .push(format!("::{cast}, "))
+            .push_bind(channel_id)
+            .push(")");
+    }
+    query
+        .push(format!(" ORDER BY {column} {direction}, s.channel_id {direction} LIMIT "))
+        .push_bind(limit as i64);
+
+    let rows = query.build().fetch_all(db).await?;
+    rows.iter()
+        .map(|row| {
+            let recipient_count: i64 = row.try_get("recipient_count")?;
+            Ok(ChannelSummary {
+                channel_id: row.try_get("channel_id")?,
+                owner: row.try_get("owner")?,
+                balance: row.try_get("balance")?,
+                remaining_capacity: row.try_get("remaining_capacity")?,
+                expiry_timestamp: unsigned_column(row, "expiry_ts")?,
+                status: parsed_column(row, "status", ChannelStatus::parse)?,
+                sequence_number: unsigned_column(row, "sequence_number")?,
+                next_sequence_number: unsigned_column(row, "sequence_number")? + 1,
+                recipient_count: recipient_count as u64,
+                updated_at: unsigned_column(row, "updated_at")?,
+            })
+        })
+        .collect()
+}
+
+/// Balances owed to `recipient` (normalized address) by live channels that are not closed,
+/// soonest expiry first.
+pub async fn load_recipient_balances(
+    db: &PgPool,
+    recipient: &str,
+) -> Result<Vec<RecipientChannelBalance>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT c.channel_id, c.owner, r.balance, {EFFECTIVE_STATUS} AS status, c.sequence_number, \
+            c.signature_timestamp, c.expiry_ts \
+         FROM recipients r JOIN channels c ON c.channel_id = r.channel_id \
+         WHERE r.recipient_address = $1 AND c.status IN ('open', 'finalizing') \
+         ORDER BY c.expiry_ts, c.channel_id"
+    ))
+    .bind(recipient)
+    .fetch_all(db)
+    .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(RecipientChannelBalance {
+                channel_id: row.try_get("channel_id")?,
+                owner: row.try_get("owner")?,
+                balance: parsed_column(row, "balance", parse_u256)?.to_string(),
+                status: parsed_column(row, "status", ChannelStatus::parse)?,
+                sequence_number: unsigned_column(row, "sequence_number")?,
+                signature_timestamp: unsigned_column(row, "signature_timestamp")?,
+                expiry_timestamp: unsigned_column(row, "expiry_ts")?,
+            })
+        })
+        .collect()
+}
+
+async fn with_recipients(
+    db: &PgPool,
+    rows: Vec<PgRow>,
+    recipients_table: &str,
+) -> Result<Vec<ChannelState>, sqlx::Error> {
+    parse_with_recipients(db, rows, recipients_table)
+        .await?
+        .into_iter()
+        .map(|(_, channel)| channel)
+        .collect()
+}
+
+async fn parse_with_recipients(
+    db: &PgPool,
+    rows: Vec<PgRow>,
+    recipients_table: &str,
+) -> Result<Vec<(String, Result<ChannelState, sqlx::Error>)>, sqlx::Error> {
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let channel_ids = rows
+        .iter()
+        .map(|row| row.try_get::<String, _>("channel_id"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let recipients_rows = sqlx::query(&format!(
+        "SELECT channel_id, recipient_address, balance, position FROM {recipients_table} \
+         WHERE channel_id = ANY($1) ORDER BY channel_id, position"
+    ))
+    .bind(&channel_ids)
+    .fetch_all(db)
+    .await?;
+
+    let mut recipients_by_channel: HashMap<String, Vec<PgRow>> = HashMap::new();
+    for recipient_row in recipients_rows {
+        let channel_id: String = recipient_row.try_get("channel_id")?;
+        recipients_by_channel.entry(channel_id).or_default().push(recipient_row);
+    }
+
+    let mut channels = Vec::with_capacity(rows.len());
+    for (row, channel_id) in rows.iter().zip(channel_ids) {
+        let channel = recipients_by_channel
+            .remove(&channel_id)
+            .unwrap_or_default()
+            .iter()
+            .map(recipient_from_row)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|recipients| channel_from_row(row, recipients));
+        channels.push((channel_id, channel));
+    }
+
+    Ok(channels)
+}
+
+fn channel_from_row(row: &PgRow, recipients: Vec<RecipientBalance>) -> Result<ChannelState, sqlx::Error> {
+    let closed_at: Option<i64> = row.try_get("closed_at")?;
+    Ok(ChannelState {
+        channel_id: parsed_column(row, "channel_id", parse_h256)?,
+        owner: parsed_column(row, "owner", parse_address)?,
+        balance: parsed_column(row, "balance", parse_u256)?,
+        expiry_ts: unsigned_column(row, "expiry_ts")?,
+        sequence_number: unsigned_column(row, "sequence_number")?,
+        user_signature: row.try_get("user_signature")?,
+        sequencer_signature: row.try_get("sequencer_signature")?,
+        signature_timestamp: unsigned_column(row, "signature_timestamp")?,
+        recipients,
+        status: parsed_column(row, "status", ChannelStatus::parse)?,
+        close_tx_hash: row.try_get("close_tx_hash")?,
+        closed_at: closed_at
+            .map(|ts| u64::try_from(ts).map_err(|err| corrupt_column("closed_at", err)))
+            .transpose()?,
+    })
+}
+
+fn recipient_from_row(row: &PgRow) -> Result<RecipientBalance, sqlx::Error> {
+    Ok(RecipientBalance {
+        recipient_address: parsed_column(row, "recipient_address", parse_address)?,
+        balance: parsed_column(row, "balance", parse_u256)?,
+        position: row.try_get("position")?,
+    })
+}
+
+/// Reads a text column and parses it strictly; a bad value is a decode error, never a default.
+fn parsed_column<T>(row: &PgRow, column: &str, parse: fn(&str) -> Result<T, AppError>) -> Result<T, sqlx::Error> {
+    let raw: String = row.try_get(column)?;
+    parse(&raw).map_err(|err| corrupt_column(column, err))
+}
+
+fn unsigned_column(row: &PgRow, column: &str) -> Result<u64, sqlx::Error> {
+    let value: i64 = row.try_get(column)?;
+    u64::try_from(value).map_err(|err| corrupt_column(column, err))
+}
+
+fn corrupt_column<E>(column: &str, source: E) -> sqlx::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: Box::new(source),
+    }
+}
+
+/// Takes a channel out of service, recording why. Replicas drop it on the notification.
+/// Fenced like `save_channel`, so a standby cannot quarantine rows the leader is serving.
+pub async fn quarantine_channel(
+    db: &PgPool,
+    channel_id: &str,
+    reason: &str,
+    quarantined_at: u64,
+    fencing_token: Option<u64>,
+) -> Result<(), WriteError> {
+    let mut tx = db.begin().await?;
+    if let Some(token) = fencing_token {
+        if current_epoch(&mut tx, true).await? != token {
+            return Err(WriteError::Fenced(token));
+        }
+    }
+    let sequence_number: Option<i64> = sqlx::query_scalar(
+        "UPDATE channels SET status = 'quarantined', quarantine_reason = $2, quarantined_at = $3 \
+         WHERE channel_id = $1 RETURNING sequence_number",
+    )
+    .bind(channel_id)
+    .bind(reason)
+    .bind(quarantined_at as i64)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some(sequence_number) = sequence_number {
+        notify_channel_update(&mut tx, channel_id, sequence_number).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn quarantine_reason(db: &PgPool, channel_id: &str) -> Result<Option<String>, sqlx::Error> {
+    let reason: Option<Option<String>> =
+        sqlx::query_scalar("SELECT quarantine_reason FROM channels WHERE channel_id = $1")
+            .bind(channel_id)
+            .fetch_optional(db)
+            .await?;
+    Ok(reason.flatten())
+}
+
+/// Lists quarantined channels as `(channel_id, reason, quarantined_at)`, most recent first.
+pub async fn load_quarantined(db: &PgPool) -> Result<Vec<(String, String, i64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT channel_id, quarantine_reason, quarantined_at FROM channels \
+         WHERE status = 'quarantined' ORDER BY quarantined_at DESC, channel_id",
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Records a recipient's request to finalize a channel unless the recipient already asked
+/// within `cooldown_secs`, returning its id, or `None` while cooling down. Requests for the
+/// same channel and recipient take a transaction advisory lock, so concurrent requests
+/// cannot both pass the check.
+pub async fn insert_finalize_request(
+    db: &PgPool,
+    channel_id: &str,
+    recipient: &str,
+    reason: &str,
+    requested_at: u64,
+    cooldown_secs: u64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let mut tx = db.begin().await?;
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1), hashtext($2))")
+        .bind(channel_id)
+        .bind(recipient)
+        .execute(&mut *tx)
+        .await?;
+    let request_id: Option<i64> = sqlx::query_scalar(
+        "INSERT INTO finalize_requests (channel_id, recipient, reason, requested_at, status) \
+         SELECT $1, $2, $3, $4, $6 WHERE NOT EXISTS (\
+            SELECT 1 FROM finalize_requests WHERE channel_id = $1 AND recipient = $2 AND requested_at > $4 - $5\
+         ) RETURNING id",
+    )
+    .bind(channel_id)
+    .bind(recipient)
+    .bind(reason)
+    .bind(requested_at as i64)
+    .bind(cooldown_secs.min(i64::MAX as u64) as i64)
+    .bind(FinalizeRequestStatus::Pending.as_str())
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(request_id)
+}
+
+/// When `recipient` last requested a finalize of `channel_id`, if ever.
+pub async fn last_finalize_request(db: &PgPool, channel_id: &str, recipient: &str) -> Result<Option<u64>, sqlx::Error> {
+    let requested_at: Option<i64> = sqlx::query_scalar(
+        "SELECT max(requested_at) FROM finalize_requests WHERE channel_id = $1 AND recipient = $2",
+    )
+    .bind(channel_id)
+    .bind(recipient)
+    .fetch_one(db)
+    .await?;
+    Ok(requested_at.map(|ts| ts as u64))
+}
+
+/// Channels with pending finalize requests, longest waiting first, and whether a batch
+/// they were part of reverted so they must be closed on their own.
+pub async fn pending_finalize_channels(db: &PgPool, limit: usize) -> Result<Vec<(String, bool)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT channel_id, bool_or(retry_alone) FROM finalize_requests WHERE status = 'pending' \
+         GROUP BY channel_id ORDER BY min(requested_at), channel_id LIMIT $1",
+    )
+    .bind(limit as i64)
+    .fetch_all(db)
+    .await
+}
+
+/// `finalizing` channels for the recovery sweep, oldest first: those with a close
+/// transaction to check, and those idle since at or before `updated_before`. The flag
+/// tells whether the channel is that stale.
+pub async fn finalizing_channels(db: &PgPool, updated_before: u64, limit: usize) -> Result<Vec<(String, bool)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT channel_id, updated_at <= $1 FROM channels \
+         WHERE status = 'finalizing' AND (close_tx_hash IS NOT NULL OR updated_at <= $1) \
+         ORDER BY updated_at, channel_id LIMIT $2",
+    )
+    .bind(updated_before as i64)
+    .bind(limit as i64)
+    .fetch_all(db)
+    .await
+}
+
+/// Marks every pending request for `channel_ids` as `submitted` (with `tx_hash`) or `failed`
+/// (with `error`).
+pub async fn resolve_finalize_requests(
+    db: &PgPool,
+    channel_ids: &[String],
+    outcome: Result<&str, &str>,
+    processed_at: u64,
+) -> Result<(), sqlx::Error> {
+    let (status, tx_hash, error) = match outcome {
+        Ok(tx_hash) => (FinalizeRequestStatus::Submitted, Some(tx_hash), None),
+        Err(error) => (FinalizeRequestStatus::Failed, None, Some(error)),
+    };
+    sqlx::query(
+        "UPDATE finalize_requests SET status = $2, tx_hash = $3, error = $4, processed_at = $5 \
+         WHERE channel_id = ANY($1) AND status = 'pending'",
+    )
+    .bind(channel_ids)
+    .bind(status.as_str())
+    .bind(tx_hash)
+    .bind(error)
+    .bind(processed_at as i64)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Marks the open requests of a channel that closed on-chain as `confirmed`.
+pub async fn confirm_finalize_requests(
+    db: &PgPool,
+    channel_id: &str,
+    tx_hash: Option<&str>,
+    processed_at: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE finalize_requests SET status = $2, tx_hash = COALESCE(tx_hash, $3), processed_at = $4 \
+         WHERE channel_id = $1 AND status IN ('pending', 'submitted')",
+    )
+    .bind(channel_id)
+    .bind(FinalizeRequestStatus::Confirmed.as_str())
+    .bind(tx_hash)
+    .bind(processed_at as i64)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Handles the submitted requests of a channel whose close transaction failed. A batch
+/// reverts as a whole, so requests of a batch that closed other channels too go back to
+/// `pending` to be closed on their own; the requests of a channel closed alone fail.
+pub async fn retry_finalize_requests(
+    db: &PgPool,
+    channel_id: &str,
+    error: &str,
+    processed_at: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE finalize_requests r SET \
+            status = CASE WHEN batched THEN $2 ELSE $3 END, \
+            retry_alone = batched, \
+            error = CASE WHEN batched THEN NULL ELSE $4 END, \
+            processed_at = CASE WHEN batched THEN NULL ELSE $5 END \
+         FROM (\
+            SELECT id, EXISTS (\
+                SELECT 1 FROM finalize_requests other \
+                WHERE other.tx_hash = own.tx_hash AND other.channel_id <> own.channel_id\
+            ) AS batched \
+            FROM finalize_requests own WHERE own.channel_id = $1 AND own.status = 'submitted'\
+         ) submitted \
+         WHERE r.id = submitted.id",
+    )
+    .bind(channel_id)
+    .bind(FinalizeRequestStatus::Pending.as_str())
+    .bind(FinalizeRequestStatus::Failed.as_str())
+    .bind(error)
+    .bind(processed_at as i64)
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Records an owner's close request for `channel_id` at `sequence_number`, returning
+/// `false` if one was already accepted for that state.
+pub async fn insert_close_request(
+    db: &PgPool,
+    channel_id: &str,
+    sequence_number: u64,
+    signature: &str,
+    timestamp: u64,
+    accepted_at: u64,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO close_requests (channel_id, sequence_number, signature, timestamp, accepted_at) \
+         VALUES ($1, $2, $3, $4, $5) ON CONFLICT DO NOTHING",
+    )
+    .bind(channel_id)
+    .bind(sequence_number as i64)
+    .bind(signature)
+    .bind(timestamp as i64)
+    .bind(accepted_at as i64)
+    .execute(db)
+    .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Forgets a close request that was recorded but could not be accepted.
+pub async fn delete_close_request(db: &PgPool, channel_id: &str, sequence_number: u64) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM close_requests WHERE channel_id = $1 AND sequence_number = $2")
+        .bind(channel_id)
+        .bind(sequence_number as i64)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Whether the owner asked to close `channel_id` at `sequence_number`.
+pub async fn close_request_accepted(db: &PgPool, channel_id: &str, sequence_number: u64) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM close_requests WHERE channel_id = $1 AND sequence_number = $2)",
+    )
+    .bind(channel_id)
+    .bind(sequence_number as i64)
+    .fetch_one(db)
+    .await
+}
+
+pub async fn insert_api_key(
+    db: &PgPool,
+    key_id: &str,
+    secret: &str,
+    role: &str,
+    label: &str,
+    created_at: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO api_keys (key_id, secret, role, label, created_at) VALUES ($1, $2, $3, $4, $5)")
+        .bind(key_id)
+        .bind(secret)
+        .bind(role)
+        .bind(label)
+        .bind(created_at as i64)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// `(key_id, secret, role)` of every key that was not revoked.
+pub async fn load_active_api_keys(db: &PgPool) -> Result<Vec<(String, String, String)>, sqlx::Error> {
+    sqlx::query_as("SELECT key_id, secret, role FROM api_keys WHERE revoked_at IS NULL")
+        .fetch_all(db)
+        .await
+}
+
+/// `(key_id, role, label, created_at, revoked_at)` of every key, oldest first. Secrets are
+/// not read.
+pub async fn list_api_keys(db: &PgPool) -> Result<Vec<(String, String, String, i64, Option<i64>)>, sqlx::Error> {
+    sqlx::query_as("SELECT key_id, role, label, created_at, revoked_at FROM api_keys ORDER BY created_at, key_id")
         .fetch_all(db)
+        .await
+}
+
+/// Revokes a key, returning whether an active key was found.
+pub async fn revoke_api_key(db: &PgPool, key_id: &str, revoked_at: u64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE api_keys SET revoked_at = $2 WHERE key_id = $1 AND revoked_at IS NULL")
+        .bind(key_id)
+        .bind(revoked_at as i64)
+        .execute(db)
         .await?;
+    Ok(result.rows_affected() > 0)
+}
 
-        let mut recipients = Vec::new();
-        for recipient_row in recipients_rows {
-            let address_str: String = recipient_row.try_get("recipient_address")?;
-            let balance_str: String = recipient_row.try_get("balance")?;
-            let position: i32 = recipient_row.try_get("position")?;
-            recipients.push(RecipientBalance {
-                recipient_address: parse_address(&address_str).unwrap_or_default(),
-                balance: parse_u256(&balance_str).unwrap_or_default(),
-                position,
-            });
-        }
+/// Stores a receiver challenge, dropping challenges that expired before `now`.
+pub async fn insert_receiver_challenge(
+    db: &PgPool,
+    nonce: &str,
+    key_id: &str,
+    receiver: &str,
+    expires_at: u64,
+    now: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM receiver_challenges WHERE expires_at < $1")
+        .bind(now as i64)
+        .execute(db)
+        .await?;
+    sqlx::query("INSERT INTO receiver_challenges (nonce, key_id, receiver, expires_at) VALUES ($1, $2, $3, $4)")
+        .bind(nonce)
+        .bind(key_id)
+        .bind(receiver)
+        .bind(expires_at as i64)
+        .execute(db)
+        .await?;
+    Ok(())
+}
 
-        let channel_state = ChannelState {
-            channel_id: parse_h256(&channel_id_str).unwrap_or_default(),
-            owner: parse_address(&owner_str).unwrap_or_default(),
-            balance: parse_u256(&balance_str).unwrap_or_default(),
-            expiry_ts: expiry_ts as u64,
-            sequence_number: sequence_number as u64,
-            user_signature,
-            sequencer_signature,
-            signature_timestamp: signature_timestamp as u64,
-            recipients,
-        };
+/// Deletes and returns the `(receiver, expires_at)` of a key's challenge, so every
+/// challenge can be answered once.
+pub async fn take_receiver_challenge(
+    db: &PgPool,
+    nonce: &str,
+    key_id: &str,
+) -> Result<Option<(String, i64)>, sqlx::Error> {
+    sqlx::query_as("DELETE FROM receiver_challenges WHERE nonce = $1 AND key_id = $2 RETURNING receiver, expires_at")
+        .bind(nonce)
+        .bind(key_id)
+        .fetch_optional(db)
+        .await
+}
 
-        map.insert(channel_id_str, channel_state);
-    }
+/// Binds a receiver to a key, returning when it was bound (earlier bindings are kept).
+pub async fn insert_receiver_binding(db: &PgPool, key_id: &str, receiver: &str, bound_at: u64) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "INSERT INTO receiver_bindings (key_id, receiver, bound_at) VALUES ($1, $2, $3) \
+         ON CONFLICT (key_id, receiver) DO UPDATE SET key_id = EXCLUDED.key_id \
+         RETURNING bound_at",
+    )
+    .bind(key_id)
+    .bind(receiver)
+    .bind(bound_at as i64)
+    .fetch_one(db)
+    .await
+}
+
+pub async fn delete_receiver_binding(db: &PgPool, key_id: &str, receiver: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM receiver_bindings WHERE key_id = $1 AND receiver = $2")
+        .bind(key_id)
+        .bind(receiver)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// `(receiver, bound_at)` of every receiver bound to a key.
+pub async fn load_receiver_bindings(db: &PgPool, key_id: &str) -> Result<Vec<(String, i64)>, sqlx::Error> {
+    sqlx::query_as("SELECT receiver, bound_at FROM receiver_bindings WHERE key_id = $1 ORDER BY bound_at, receiver")
+        .bind(key_id)
+        .fetch_all(db)
+        .await
+}
+
+/// `(key_id, receiver)` of every binding of a key that was not revoked.
+pub async fn load_active_receiver_bindings(db: &PgPool) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT b.key_id, b.receiver FROM receiver_bindings b \
+         JOIN api_keys k ON k.key_id = b.key_id WHERE k.revoked_at IS NULL",
+    )
+    .fetch_all(db)
+    .await
+}
 
-    Ok(map)
+/// Stores a SIWE nonce with the message issued for it, dropping nonces that expired
+/// before `now`.
+pub async fn insert_siwe_nonce(
+    db: &PgPool,
+    nonce: &str,
+    address: &str,
+    message: &str,
+    expires_at: u64,
+    now: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM siwe_nonces WHERE expires_at < $1")
+        .bind(now as i64)
+        .execute(db)
+        .await?;
+    sqlx::query("INSERT INTO siwe_nonces (nonce, address, message, expires_at) VALUES ($1, $2, $3, $4)")
+        .bind(nonce)
+        .bind(address)
+        .bind(message)
+        .bind(expires_at as i64)
+        .execute(db)
+        .await?;
+    Ok(())
 }
 
-pub async fn save_channel(db: &PgPool, channel: &ChannelState) -> Result<(), sqlx::Error> {
+/// Deletes and returns the `(address, message, expires_at)` of a SIWE nonce, so every
+/// nonce signs in once.
+pub async fn take_siwe_nonce(db: &PgPool, nonce: &str) -> Result<Option<(String, String, i64)>, sqlx::Error> {
+    sqlx::query_as("DELETE FROM siwe_nonces WHERE nonce = $1 RETURNING address, message, expires_at")
+        .bind(nonce)
+        .fetch_optional(db)
+        .await
+}
+
+#[derive(Debug, Error)]
+pub enum WriteError {
+    #[error("fencing token {0} is no longer current")]
+    Fenced(u64),
+    /// The stored row was quarantined after the writer loaded it.
+    #[error("channel {0} is quarantined")]
+    Quarantined(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+pub async fn save_channel(
+    db: &PgPool,
+    channel: &ChannelState,
+    fencing_token: Option<u64>,
+) -> Result<(), WriteError> {
+    let mut tx = db.begin().await?;
+    if let Some(token) = fencing_token {
+        if current_epoch(&mut tx, true).await? != token {
+            return Err(WriteError::Fenced(token));
+        }
+    }
+    if !write_channel(&mut tx, channel).await? {
+        return Err(WriteError::Quarantined(format!("0x{:x}", channel.channel_id)));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Replaces a quarantined row with a freshly seeded state, returning the channel to
+/// service. The only write that may overwrite a quarantined row.
+pub async fn reseed_channel(
+    db: &PgPool,
+    channel: &ChannelState,
+    fencing_token: Option<u64>,
+) -> Result<(), WriteError> {
+    let mut tx = db.begin().await?;
+    if let Some(token) = fencing_token {
+        if current_epoch(&mut tx, true).await? != token {
+            return Err(WriteError::Fenced(token));
+        }
+    }
     sqlx::query(
-        "INSERT INTO channels (channel_id, owner, balance, expiry_ts, sequence_number, user_signature, sequencer_signature, signature_timestamp)\
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)\
+        "UPDATE channels SET status = 'open', quarantine_reason = NULL, quarantined_at = NULL \
+         WHERE channel_id = $1 AND status = 'quarantined'",
+    )
+    .bind(format!("0x{:x}", channel.channel_id))
+    .execute(&mut *tx)
+    .await?;
+    if !write_channel(&mut tx, channel).await? {
+        return Err(WriteError::Quarantined(format!("0x{:x}", channel.channel_id)));
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Reads the leader epoch, which doubles as the fencing token for writes.
+///
+/// With `for_share`, writers hold a shared row lock until they commit, so a new leader
+/// bumping the epoch waits for in-flight writes and every later write from the old
+/// leader is rejected.
+pub async fn current_epoch(conn: &mut PgConnection, for_share: bool) -> Result<u64, sqlx::Error> {
+    let query = if for_share {
+        "SELECT epoch FROM sequencer_leader WHERE id = 1 FOR SHARE"
+    } else {
+        "SELECT epoch FROM sequencer_leader WHERE id = 1"
+    };
+    let epoch: i64 = sqlx::query_scalar(query).fetch_one(conn).await?;
+    Ok(epoch as u64)
+}
+
+/// Bumps the epoch for a node that has just taken the leader advisory lock.
+pub async fn claim_leadership(conn: &mut PgConnection, holder: &str) -> Result<u64, sqlx::Error> {
+    let epoch: i64 = sqlx::query_scalar(
+        "UPDATE sequencer_leader SET epoch = epoch + 1, holder = $1, acquired_at = now() WHERE id = 1 RETURNING epoch",
+    )
+    .bind(holder)
+    .fetch_one(conn)
+    .await?;
+    Ok(epoch as u64)
+}
+
+pub async fn current_leader(conn: &mut PgConnection) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar("SELECT holder FROM sequencer_leader WHERE id = 1")
+        .fetch_one(conn)
+        .await
+}
+
+/// Writes a channel and its recipients on an open connection or transaction. A quarantined
+/// row is left alone and `false` returned; only `reseed_channel` returns it to service.
+pub async fn write_channel(conn: &mut PgConnection, channel: &ChannelState) -> Result<bool, sqlx::Error> {
+    let written = sqlx::query(
+        "INSERT INTO channels (channel_id, owner, balance, expiry_ts, sequence_number, user_signature, sequencer_signature, signature_timestamp, status, close_tx_hash, closed_at, updated_at)\
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, extract(epoch FROM now())::bigint)\
          ON CONFLICT (channel_id) DO UPDATE SET \
             owner = EXCLUDED.owner,\
             balance = EXCLUDED.balance,\
@@ -108,7 +1108,12 @@ pub async fn save_channel(db: &PgPool, channel: &ChannelState) -> Result<(), sql
             sequence_number = EXCLUDED.sequence_number,\
             user_signature = EXCLUDED.user_signature,\
             sequencer_signature = EXCLUDED.sequencer_signature,\
-            signature_timestamp = EXCLUDED.signature_timestamp",
+            signature_timestamp = EXCLUDED.signature_timestamp,\
+            status = EXCLUDED.status,\
+            close_tx_hash = EXCLUDED.close_tx_hash,\
+            closed_at = EXCLUDED.closed_at,\
+            updated_at = EXCLUDED.updated_at \
+         WHERE channels.status <> 'quarantined'",
     )
     .bind(format!("0x{:x}", channel.channel_id))
     .bind(format!("0x{:x}", channel.owner))
@@ -118,8 +1123,27 @@ pub async fn save_channel(db: &PgPool, channel: &ChannelState) -> Result<(), sql
     .bind(channel.user_signature.clone())
     .bind(channel.sequencer_signature.clone())
     .bind(channel.signature_timestamp as i64)
-    .execute(db)
-    .await?;
+    .bind(channel.status.as_str())
+    .bind(channel.close_tx_hash.clone())
+    .bind(channel.closed_at.map(|ts| ts as i64))
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if written == 0 {
+        return Ok(false);
+    }
+
+    // Drop recipients that are no longer part of the state (e.g. after a re-seed).
+    let recipient_addresses: Vec<String> = channel
+        .recipients
+        .iter()
+        .map(|r| format!("0x{:x}", r.recipient_address))
+        .collect();
+    sqlx::query("DELETE FROM recipients WHERE channel_id = $1 AND NOT (recipient_address = ANY($2))")
+        .bind(format!("0x{:x}", channel.channel_id))
+        .bind(&recipient_addresses)
+        .execute(&mut *conn)
+        .await?;
 
     for recipient in &channel.recipients {
         sqlx::query(
@@ -133,9 +1157,378 @@ pub async fn save_channel(db: &PgPool, channel: &ChannelState) -> Result<(), sql
         .bind(format!("0x{:x}", recipient.recipient_address))
         .bind(recipient.balance.to_string())
         .bind(recipient.position)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    notify_channel_update(conn, &format!("0x{:x}", channel.channel_id), channel.sequence_number as i64).await?;
+    Ok(true)
+}
+
+/// Open channels expired at or before `expired_before` that still owe recipients. They can
+/// only pay out through a sequencer close, so they are finalized before being archived.
+pub async fn expired_channels_owing(db: &PgPool, expired_before: u64, limit: usize) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT channel_id FROM channels \
+         WHERE status = 'open' AND expiry_ts <= $1 AND EXISTS (\
+            SELECT 1 FROM recipients WHERE recipients.channel_id = channels.channel_id\
+         ) \
+         ORDER BY expiry_ts, channel_id LIMIT $2",
+    )
+    .bind(expired_before as i64)
+    .bind(limit as i64)
+    .fetch_all(db)
+    .await
+}
+
+/// Moves up to `limit` channels that were closed, or expired without owing any recipient,
+/// at or before `cutoff` (unix seconds) into the archive tables, returning their ids.
+/// Channels still being written by another transaction are skipped until the next sweep.
+pub async fn archive_channels(
+    db: &PgPool,
+    cutoff: u64,
+    archived_at: u64,
+    limit: usize,
+    fencing_token: Option<u64>,
+) -> Result<Vec<String>, WriteError> {
+    let mut tx = db.begin().await?;
+    if let Some(token) = fencing_token {
+        if current_epoch(&mut tx, true).await? != token {
+            return Err(WriteError::Fenced(token));
+        }
+    }
+
+    let candidates: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT channel_id, sequence_number FROM channels \
+         WHERE (status = 'closed' AND closed_at <= $1) \
+            OR (status = 'open' AND expiry_ts <= $1 AND NOT EXISTS (\
+                SELECT 1 FROM recipients WHERE recipients.channel_id = channels.channel_id\
+            )) \
+         ORDER BY channel_id LIMIT $2 FOR UPDATE SKIP LOCKED",
+    )
+    .bind(cutoff as i64)
+    .bind(limit as i64)
+    .fetch_all(&mut *tx)
+    .await?;
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+    let channel_ids: Vec<String> = candidates.iter().map(|(channel_id, _)| channel_id.clone()).collect();
+
+    // A channel can be archived twice if a late write recreated it; the newer state wins.
+    sqlx::query(&format!(
+        "INSERT INTO archived_channels ({CHANNEL_COLUMNS}, archived_at) \
+         SELECT {CHANNEL_COLUMNS}, $2 FROM channels WHERE channel_id = ANY($1) \
+         ON CONFLICT (channel_id) DO UPDATE SET \
+            owner = EXCLUDED.owner,\
+            balance = EXCLUDED.balance,\
+            expiry_ts = EXCLUDED.expiry_ts,\
+            sequence_number = EXCLUDED.sequence_number,\
+            user_signature = EXCLUDED.user_signature,\
+            sequencer_signature = EXCLUDED.sequencer_signature,\
+            signature_timestamp = EXCLUDED.signature_timestamp,\
+            status = EXCLUDED.status,\
+            close_tx_hash = EXCLUDED.close_tx_hash,\
+            closed_at = EXCLUDED.closed_at,\
+            archived_at = EXCLUDED.archived_at"
+    ))
+    .bind(&channel_ids)
+    .bind(archived_at as i64)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM archived_recipients WHERE channel_id = ANY($1)")
+        .bind(&channel_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO archived_recipients (channel_id, recipient_address, balance, position) \
+         SELECT channel_id, recipient_address, balance, position FROM recipients WHERE channel_id = ANY($1)",
+    )
+    .bind(&channel_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    // Recipients go with their channel through `ON DELETE CASCADE`.
+    sqlx::query("DELETE FROM channels WHERE channel_id = ANY($1)")
+        .bind(&channel_ids)
+        .execute(&mut *tx)
+        .await?;
+
+    for (channel_id, sequence_number) in &candidates {
+        notify_channel_update(&mut tx, channel_id, *sequence_number).await?;
+    }
+
+    tx.commit().await?;
+    Ok(channel_ids)
+}
+
+/// Deletes archived channels (and their recipients) archived at or before `archived_before`.
+pub async fn purge_archive(db: &PgPool, archived_before: u64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM archived_channels WHERE archived_at <= $1")
+        .bind(archived_before as i64)
         .execute(db)
         .await?;
+    Ok(result.rows_affected())
+}
+
+/// Publishes a channel change on [`CHANNEL_UPDATES`]. Delivered to listeners only when the
+/// surrounding transaction commits.
+async fn notify_channel_update(
+    conn: &mut PgConnection,
+    channel_id: &str,
+    sequence_number: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "SELECT pg_notify($1, json_build_object(\
+            'channelId', $2::text,\
+            'sequenceNumber', $3::bigint,\
+            'committedAtMs', (extract(epoch FROM clock_timestamp()) * 1000)::bigint\
+        )::text)",
+    )
+    .bind(CHANNEL_UPDATES)
+    .bind(channel_id)
+    .bind(sequence_number)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Reads every live (or archived) channel row as stored, including quarantined rows.
+pub async fn load_channel_records(db: &PgPool, archived: bool) -> Result<Vec<ChannelRecord>, sqlx::Error> {
+    let (channels_query, recipients_query) = if archived {
+        (
+            format!("SELECT {CHANNEL_COLUMNS}, NULL::TEXT AS quarantine_reason, NULL::BIGINT AS quarantined_at, archived_at FROM archived_channels ORDER BY channel_id"),
+            "SELECT channel_id, recipient_address, balance, position FROM archived_recipients ORDER BY channel_id, position",
+        )
+    } else {
+        (
+            format!("SELECT {CHANNEL_COLUMNS}, quarantine_reason, quarantined_at, NULL::BIGINT AS archived_at FROM channels ORDER BY channel_id"),
+            "SELECT channel_id, recipient_address, balance, position FROM recipients ORDER BY channel_id, position",
+        )
+    };
+
+    let mut recipients_by_channel: HashMap<String, Vec<RecipientRecord>> = HashMap::new();
+    for row in sqlx::query(recipients_query).fetch_all(db).await? {
+        let channel_id: String = row.try_get("channel_id")?;
+        recipients_by_channel.entry(channel_id).or_default().push(RecipientRecord {
+            recipient_address: row.try_get("recipient_address")?,
+            balance: row.try_get("balance")?,
+            position: row.try_get("position")?,
+        });
     }
 
+    let rows = sqlx::query(&channels_query).fetch_all(db).await?;
+    let mut records = Vec::with_capacity(rows.len());
+    for row in rows {
+        let channel_id: String = row.try_get("channel_id")?;
+        records.push(ChannelRecord {
+            recipients: recipients_by_channel.remove(&channel_id).unwrap_or_default(),
+            channel_id,
+            owner: row.try_get("owner")?,
+            balance: row.try_get("balance")?,
+            expiry_ts: row.try_get("expiry_ts")?,
+            sequence_number: row.try_get("sequence_number")?,
+            user_signature: row.try_get("user_signature")?,
+            sequencer_signature: row.try_get("sequencer_signature")?,
+            signature_timestamp: row.try_get("signature_timestamp")?,
+            status: row.try_get("status")?,
+            close_tx_hash: row.try_get("close_tx_hash")?,
+            closed_at: row.try_get("closed_at")?,
+            quarantine_reason: row.try_get("quarantine_reason")?,
+            quarantined_at: row.try_get("quarantined_at")?,
+            archived_at: row.try_get("archived_at")?,
+        });
+    }
+    Ok(records)
+}
+
+/// Inserts channel rows verbatim into the live tables, or into the archive tables for
+/// records that carry `archived_at`.
+pub async fn insert_channel_records(conn: &mut PgConnection, records: &[ChannelRecord]) -> Result<(), sqlx::Error> {
+    for record in records {
+        let (channel_insert, recipients_table) = match record.archived_at {
+            Some(_) => (
+                format!(
+                    "INSERT INTO archived_channels ({CHANNEL_COLUMNS}, archived_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+                ),
+                "archived_recipients",
+            ),
+            None => (
+                format!(
+                    "INSERT INTO channels ({CHANNEL_COLUMNS}, quarantine_reason, quarantined_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"
+                ),
+                "recipients",
+            ),
+        };
+        let query = sqlx::query(&channel_insert)
+            .bind(&record.channel_id)
+            .bind(&record.owner)
+            .bind(&record.balance)
+            .bind(record.expiry_ts)
+            .bind(record.sequence_number)
+            .bind(&record.user_signature)
+            .bind(&record.sequencer_signature)
+            .bind(record.signature_timestamp)
+            .bind(&record.status)
+            .bind(&record.close_tx_hash)
+            .bind(record.closed_at);
+        let query = match record.archived_at {
+            Some(archived_at) => query.bind(archived_at),
+            None => query.bind(&record.quarantine_reason).bind(record.quarantined_at),
+        };
+        query.execute(&mut *conn).await?;
+
+        for recipient in &record.recipients {
+            sqlx::query(&format!(
+                "INSERT INTO {recipients_table} (channel_id, recipient_address, balance, position) VALUES ($1, $2, $3, $4)"
+            ))
+            .bind(&record.channel_id)
+            .bind(&recipient.recipient_address)
+            .bind(&recipient.balance)
+            .bind(recipient.position)
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
     Ok(())
 }
+
+/// Number of rows in `channels` and `archived_channels`.
+pub async fn channel_counts(db: &PgPool) -> Result<(i64, i64), sqlx::Error> {
+    sqlx::query_as("SELECT (SELECT count(*) FROM channels), (SELECT count(*) FROM archived_channels)")
+        .fetch_one(db)
+        .await
+}
+
+/// Effective status of each stored live channel among `channel_ids`, quarantined ones included.
+pub async fn channel_statuses(
+    db: &PgPool,
+    channel_ids: &[String],
+) -> Result<HashMap<String, ChannelStatus>, sqlx::Error> {
+    let rows = sqlx::query(&format!(
+        "SELECT channel_id, {EFFECTIVE_STATUS} AS status FROM channels WHERE channel_id = ANY($1)"
+    ))
+    .bind(channel_ids)
+    .fetch_all(db)
+    .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get("channel_id")?, parsed_column(row, "status", ChannelStatus::parse)?)))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ethers_core::types::{Address, H256, U256};
+
+    use super::*;
+
+    /// Database for tests that need Postgres, from `TEST_DATABASE_URL`. Without it those
+    /// tests return early and pass.
+    pub(crate) async fn test_db() -> Option<PgPool> {
+        static SCHEMA: tokio::sync::Mutex<bool> = tokio::sync::Mutex::const_new(false);
+        let url = std::env::var("TEST_DATABASE_URL").ok()?;
+        let db = PgPool::connect(&url).await.expect("connect to TEST_DATABASE_URL");
+        let mut initialized = SCHEMA.lock().await;
+        if !*initialized {
+            init_db(&db).await.expect("create schema");
+            *initialized = true;
+        }
+        Some(db)
+    }
+
+    /// A channel with a fresh id owing `recipients` recipients 10 each.
+    pub(crate) fn channel(status: ChannelStatus, expiry_ts: u64, recipients: usize) -> ChannelState {
+        ChannelState {
+            channel_id: H256::random(),
+            owner: Address::random(),
+            balance: U256::from(1_000),
+            expiry_ts,
+            sequence_number: recipients as u64,
+            user_signature: String::new(),
+            sequencer_signature: String::new(),
+            signature_timestamp: 0,
+            recipients: (0..recipients)
+                .map(|position| RecipientBalance {
+                    recipient_address: Address::random(),
+                    balance: U256::from(10),
+                    position: position as i32,
+                })
+                .collect(),
+            status,
+            close_tx_hash: None,
+            closed_at: None,
+        }
+    }
+
+    fn key(channel: &ChannelState) -> String {
+        format!("0x{:x}", channel.channel_id)
+    }
+
+    #[tokio::test]
+    async fn expired_channels_owing_recipients_are_not_archived() {
+        let Some(db) = test_db().await else { return };
+        let owing = channel(ChannelStatus::Open, 1_000, 2);
+        let empty = channel(ChannelStatus::Open, 1_000, 0);
+        let mut closed = channel(ChannelStatus::Closed, 1_000, 2);
+        closed.closed_at = Some(1_000);
+        for channel in [&owing, &empty, &closed] {
+            save_channel(&db, channel, None).await.unwrap();
+        }
+
+        assert!(expired_channels_owing(&db, 2_000, 10_000).await.unwrap().contains(&key(&owing)));
+        let mut archived = Vec::new();
+        loop {
+            let batch = archive_channels(&db, 2_000, 3_000, 500, None).await.unwrap();
+            if batch.is_empty() {
+                break;
+            }
+            archived.extend(batch);
+        }
+        assert!(!archived.contains(&key(&owing)));
+        assert!(archived.contains(&key(&empty)));
+        assert!(archived.contains(&key(&closed)));
+        assert!(load_channel(&db, &key(&owing)).await.unwrap().is_some());
+    }
+
+    async fn request_statuses(db: &PgPool, channel_id: &str) -> Vec<(String, bool)> {
+        sqlx::query_as("SELECT status, retry_alone FROM finalize_requests WHERE channel_id = $1 ORDER BY id")
+            .bind(channel_id)
+            .fetch_all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn reverted_batch_requests_are_retried_alone() {
+        let Some(db) = test_db().await else { return };
+        let first = key(&channel(ChannelStatus::Open, u64::MAX >> 1, 1));
+        let second = key(&channel(ChannelStatus::Open, u64::MAX >> 1, 1));
+        for channel_id in [&first, &second] {
+            insert_finalize_request(&db, channel_id, "0xrecipient", "", 1_000, 0).await.unwrap();
+        }
+        let batch_hash = format!("0x{:x}", H256::random());
+        resolve_finalize_requests(&db, &[first.clone(), second.clone()], Ok(&batch_hash), 1_001).await.unwrap();
+
+        // The whole batch reverted: both channels go back to pending, to be closed alone.
+        for channel_id in [&first, &second] {
+            retry_finalize_requests(&db, channel_id, "close transaction reverted", 1_002).await.unwrap();
+            assert_eq!(request_statuses(&db, channel_id).await, vec![("pending".to_string(), true)]);
+        }
+        let pending = pending_finalize_channels(&db, 10_000).await.unwrap();
+        assert!(pending.contains(&(first.clone(), true)));
+
+        // Its own close reverts too: now the request fails.
+        let solo_hash = format!("0x{:x}", H256::random());
+        resolve_finalize_requests(&db, std::slice::from_ref(&first), Ok(&solo_hash), 1_003).await.unwrap();
+        retry_finalize_requests(&db, &first, "close transaction reverted", 1_004).await.unwrap();
+        assert_eq!(request_statuses(&db, &first).await[0].0, "failed");
+
+        resolve_finalize_requests(&db, std::slice::from_ref(&second), Ok(&solo_hash), 1_005).await.unwrap();
+        confirm_finalize_requests(&db, &second, Some(&solo_hash), 1_006).await.unwrap();
+        assert_eq!(request_statuses(&db, &second).await[0].0, "confirmed");
+    }
+}
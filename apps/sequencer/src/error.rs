@@ -1,14 +1,35 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    http::{header::RETRY_AFTER, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde_json::json;
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
+
+use crate::db::WriteError;
 
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("bad request: {0}")]
     BadRequest(String),
+    /// A well-formed signature that failed recovery or recovered to the wrong signer; a
+    /// `400` that also counts towards the caller's signature-failure ban.
+    #[error("bad request: {0}")]
+    InvalidSignature(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("forbidden: {0}")]
+    Forbidden(String),
     #[error("not found: {0}")]
     NotFound(String),
+    #[error("conflict: {0}")]
+    Conflict(String),
+    #[error("unavailable: {0}")]
+    Unavailable(String),
+    /// Rate limited or banned; the client may retry after `retry_after_secs`.
+    #[error("too many requests: {message}")]
+    TooManyRequests { message: String, retry_after_secs: u64 },
     #[error("internal error")]
     Internal,
 }
@@ -18,9 +39,36 @@ impl AppError {
         Self::BadRequest(msg.to_string())
     }
 
+    pub fn invalid_signature<T: ToString>(msg: T) -> Self {
+        Self::InvalidSignature(msg.to_string())
+    }
+
+    pub fn unauthorized<T: ToString>(msg: T) -> Self {
+        Self::Unauthorized(msg.to_string())
+    }
+
+    pub fn forbidden<T: ToString>(msg: T) -> Self {
+        Self::Forbidden(msg.to_string())
+    }
+
     pub fn not_found<T: ToString>(msg: T) -> Self {
         Self::NotFound(msg.to_string())
     }
+
+    pub fn conflict<T: ToString>(msg: T) -> Self {
+        Self::Conflict(msg.to_string())
+    }
+
+    pub fn unavailable<T: ToString>(msg: T) -> Self {
+        Self::Unavailable(msg.to_string())
+    }
+
+    pub fn too_many_requests<T: ToString>(msg: T, retry_after_secs: u64) -> Self {
+        Self::TooManyRequests {
+            message: msg.to_string(),
+            retry_after_secs,
+        }
+    }
 }
 
 impl From<sqlx::Error> for AppError {
@@ -30,11 +78,38 @@ impl From<sqlx::Error> for AppError {
     }
 }
 
+impl From<WriteError> for AppError {
+    fn from(err: WriteError) -> Self {
+        match err {
+            WriteError::Fenced(token) => {
+                warn!(token, "write rejected by fencing token");
+                AppError::unavailable("sequencer lost leadership")
+            }
+            WriteError::Quarantined(channel_id) => {
+                warn!(channel_id = %channel_id, "write rejected; channel was quarantined");
+                AppError::conflict("channel is quarantined")
+            }
+            WriteError::Database(err) => err.into(),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         let (status, message) = match self {
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::BadRequest(msg) | AppError::InvalidSignature(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            AppError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            AppError::TooManyRequests {
+                message,
+                retry_after_secs,
+            } => {
+                let headers = [(RETRY_AFTER, retry_after_secs.max(1).to_string())];
+                return (StatusCode::TOO_MANY_REQUESTS, headers, Json(json!({ "error": message }))).into_response();
+            }
             AppError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal error".to_string()),
         };
 
@@ -0,0 +1,144 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use crate::{
+    db::{finalizing_channels, pending_finalize_channels, resolve_finalize_requests},
+    error::AppError,
+    service::{
+        begin_finalize, complete_finalize, final_close_batch_transaction, recover_finalize, send_final_close, AppState,
+    },
+};
+
+/// How long a channel may stay `finalizing` without a known close transaction before the
+/// recovery sweep treats it as abandoned; longer than any close this process still has
+/// in flight.
+const FINALIZE_RECOVERY_GRACE_SECS: u64 = 120;
+
+/// Closes channels that recipients asked to finalize (see `service::request_finalize`).
+///
+/// Runs on the leader only. Every `interval`, up to `max_batch_size` channels with pending
+/// requests are moved to `finalizing` and closed together with one
+/// `finalCloseBySequencerBatch` transaction. Channels that can no longer be finalized fail
+/// their requests on their own; if the transaction cannot be signed, every channel in the
+/// batch reverts to `open` and its requests fail. Requests of a sent batch stay
+/// `submitted` until the receipt is in.
+///
+/// Each tick first sweeps `finalizing` channels (see `service::recover_finalize`): those
+/// with a close transaction are closed once it is mined, or reopened if it reverted, and
+/// those left without one for longer than `FINALIZE_RECOVERY_GRACE_SECS`, including by a
+/// previous leader that died, are recovered from the chain. A reverted batch sends its
+/// requests back to be closed one channel at a time, so a single bad channel cannot hold
+/// up the others.
+pub struct FinalizeBatcher {
+    pub state: AppState,
+    pub interval: Duration,
+    pub max_batch_size: usize,
+}
+
+impl FinalizeBatcher {
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            let Ok(fencing_token) = self.state.leadership.ensure_leader() else {
+                continue;
+            };
+            if let Err(err) = self.recover(fencing_token).await {
+                warn!(error = %err, "finalize recovery failed");
+            }
+            if let Err(err) = self.flush(fencing_token).await {
+                warn!(error = %err, "finalize batch failed");
+            }
+        }
+    }
+
+    async fn recover(&self, fencing_token: Option<u64>) -> Result<(), AppError> {
+        let updated_before = now_secs().saturating_sub(FINALIZE_RECOVERY_GRACE_SECS);
+        let channels = finalizing_channels(&self.state.db, updated_before, self.max_batch_size.max(1)).await?;
+        for (channel_id, stale) in channels {
+            match recover_finalize(&self.state, &channel_id, stale, fencing_token).await {
+                Ok(Some(status)) => info!(channel_id = %channel_id, status = status.as_str(), "finalizing channel settled"),
+                Ok(None) => {}
+                Err(err) => warn!(channel_id = %channel_id, error = %err, "could not recover finalizing channel"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn flush(&self, fencing_token: Option<u64>) -> Result<(), AppError> {
+        let channels = pending_finalize_channels(&self.state.db, self.max_batch_size.max(1)).await?;
+        let (alone, batched): (Vec<_>, Vec<_>) = channels.into_iter().partition(|(_, alone)| *alone);
+        let batches = std::iter::once(batched.into_iter().map(|(channel_id, _)| channel_id).collect())
+            .chain(alone.into_iter().map(|(channel_id, _)| vec![channel_id]));
+
+        let mut failed = None;
+        for channel_ids in batches {
+            if let Err(err) = self.close(channel_ids, fencing_token).await {
+                failed.get_or_insert(err);
+            }
+        }
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Closes `channel_ids` with one batch transaction.
+    async fn close(&self, channel_ids: Vec<String>, fencing_token: Option<u64>) -> Result<(), AppError> {
+        let db = &self.state.db;
+        let mut batch_ids = Vec::with_capacity(channel_ids.len());
+        let mut handles = Vec::with_capacity(channel_ids.len());
+        let mut channels = Vec::with_capacity(channel_ids.len());
+        for channel_id in channel_ids {
+            match begin_finalize(&self.state, &channel_id, None, fencing_token).await {
+                Ok((handle, channel)) => {
+                    batch_ids.push(channel_id);
+                    handles.push(handle);
+                    channels.push(channel);
+                }
+                Err(err) => {
+                    warn!(channel_id = %channel_id, error = %err, "finalize request rejected");
+                    resolve_finalize_requests(db, &[channel_id], Err(&err.to_string()), now_secs()).await?;
+                }
+            }
+        }
+        if channels.is_empty() {
+            return Ok(());
+        }
+
+        let submitted = match final_close_batch_transaction(&self.state, &channels) {
+            Ok(transaction) => send_final_close(&self.state, transaction, &handles, fencing_token).await,
+            Err(err) => Err(err),
+        };
+        match &submitted {
+            Ok(transaction_hash) => {
+                info!(channels = batch_ids.len(), transaction_hash = %transaction_hash, "finalize batch sent")
+            }
+            Err(err) => warn!(channels = batch_ids.len(), error = %err, "finalize batch not sent"),
+        }
+        // Every channel of the batch is completed and its requests resolved even if one
+        // write fails; a channel that cannot be completed is left to `recover`. A channel
+        // with a stored transaction hash waits for the receipt, whether or not the
+        // broadcast reported success.
+        let mut incomplete = None;
+        for (channel_id, handle) in batch_ids.iter().zip(&handles) {
+            if let Err(err) = complete_finalize(&self.state, handle, &submitted, fencing_token).await {
+                warn!(channel_id = %channel_id, error = %err, "could not complete finalize");
+                incomplete.get_or_insert(err);
+            }
+            let outcome = match (&handle.lock().await.close_tx_hash, &submitted) {
+                (Some(transaction_hash), _) => Ok(transaction_hash.clone()),
+                (None, Err(err)) => Err(err.to_string()),
+                (None, Ok(_)) => continue,
+            };
+            let outcome = outcome.as_deref().map_err(String::as_str);
+            resolve_finalize_requests(db, std::slice::from_ref(channel_id), outcome, now_secs()).await?;
+        }
+        incomplete.map_or(Ok(()), Err)
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,191 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use sqlx::{Connection, PgConnection, PgPool};
+use tracing::{info, warn};
+
+use crate::{
+    cache::ChannelCache,
+    db::{claim_leadership, current_epoch, current_leader},
+    error::AppError,
+    model::LeadershipMetrics,
+};
+
+/// Leadership state of this sequencer process.
+///
+/// Without HA the process is always the leader and writes carry no fencing token. With HA,
+/// only the node holding the Postgres advisory lock accepts writes, and every write is
+/// checked against the epoch it was elected with (see `db::save_channel`).
+pub struct Leadership {
+    enabled: bool,
+    replica: bool,
+    node_id: String,
+    is_leader: AtomicBool,
+    epoch: AtomicU64,
+    leader: RwLock<String>,
+    transitions: AtomicU64,
+}
+
+impl Leadership {
+    pub fn standalone() -> Self {
+        Self {
+            enabled: false,
+            replica: false,
+            node_id: String::new(),
+            is_leader: AtomicBool::new(true),
+            epoch: AtomicU64::new(0),
+            leader: RwLock::new(String::new()),
+            transitions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn standby(node_id: String) -> Self {
+        Self {
+            enabled: true,
+            replica: false,
+            node_id,
+            is_leader: AtomicBool::new(false),
+            epoch: AtomicU64::new(0),
+            leader: RwLock::new(String::new()),
+            transitions: AtomicU64::new(0),
+        }
+    }
+
+    /// Read-only replica: never takes part in elections and never accepts writes.
+    pub fn replica() -> Self {
+        Self {
+            enabled: false,
+            replica: true,
+            node_id: String::new(),
+            is_leader: AtomicBool::new(false),
+            epoch: AtomicU64::new(0),
+            leader: RwLock::new(String::new()),
+            transitions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::Acquire)
+    }
+
+    /// Rejects writes on standbys; on the leader returns the fencing token to write with.
+    pub fn ensure_leader(&self) -> Result<Option<u64>, AppError> {
+        if self.replica {
+            return Err(AppError::unavailable("sequencer is a read-only replica"));
+        }
+        if !self.is_leader() {
+            let leader = self.leader.read().expect("leader lock poisoned").clone();
+            if leader.is_empty() {
+                return Err(AppError::unavailable("sequencer is a standby and no leader is known"));
+            }
+            return Err(AppError::unavailable(format!("sequencer is a standby; leader is {leader}")));
+        }
+        Ok(self.enabled.then(|| self.epoch.load(Ordering::Acquire)))
+    }
+
+    pub fn metrics(&self) -> LeadershipMetrics {
+        LeadershipMetrics {
+            ha_enabled: self.enabled,
+            role: if self.replica {
+                "replica"
+            } else if self.is_leader() {
+                "leader"
+            } else {
+                "standby"
+            }
+            .to_string(),
+            node_id: self.node_id.clone(),
+            epoch: self.epoch.load(Ordering::Acquire),
+            leader: self.leader.read().expect("leader lock poisoned").clone(),
+            transitions: self.transitions.load(Ordering::Relaxed),
+        }
+    }
+
+    fn promote(&self, epoch: u64) {
+        self.epoch.store(epoch, Ordering::Release);
+        *self.leader.write().expect("leader lock poisoned") = self.node_id.clone();
+        self.is_leader.store(true, Ordering::Release);
+        self.transitions.fetch_add(1, Ordering::Relaxed);
+        info!(epoch, node_id = %self.node_id, "became leader");
+    }
+
+    fn step_down(&self) {
+        if self.is_leader.swap(false, Ordering::AcqRel) {
+            self.transitions.fetch_add(1, Ordering::Relaxed);
+            warn!(node_id = %self.node_id, "stepped down from leader");
+        }
+    }
+
+    fn observe_leader(&self, leader: String) {
+        *self.leader.write().expect("leader lock poisoned") = leader;
+    }
+}
+
+pub struct Election {
+    pub leadership: Arc<Leadership>,
+    pub database_url: String,
+    pub db: PgPool,
+    pub channels: Arc<ChannelCache>,
+    pub lock_key: i64,
+    pub poll_interval: Duration,
+}
+
+impl Election {
+    /// Runs leader election forever. The advisory lock lives on a dedicated connection,
+    /// so losing that connection releases leadership; standbys refresh their cache on
+    /// every tick so a promotion starts warm.
+    pub async fn run(self) {
+        let mut conn: Option<PgConnection> = None;
+        let mut ticker = tokio::time::interval(self.poll_interval);
+        loop {
+            ticker.tick().await;
+            if let Err(err) = self.tick(&mut conn).await {
+                warn!(error = %err, "leader election tick failed");
+                self.leadership.step_down();
+                // Dropping the session releases the advisory lock if we still held it.
+                conn = None;
+            }
+        }
+    }
+
+    async fn tick(&self, conn: &mut Option<PgConnection>) -> Result<(), sqlx::Error> {
+        let conn = match conn {
+            Some(conn) => conn,
+            None => conn.insert(PgConnection::connect(&self.database_url).await?),
+        };
+
+        if self.leadership.is_leader() {
+            let epoch = current_epoch(conn, false).await?;
+            if epoch != self.leadership.epoch.load(Ordering::Acquire) {
+                self.leadership.step_down();
+                sqlx::query("SELECT pg_advisory_unlock($1)")
+                    .bind(self.lock_key)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let acquired: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+            .bind(self.lock_key)
+            .fetch_one(&mut *conn)
+            .await?;
+        if !acquired {
+            self.leadership.observe_leader(current_leader(conn).await?);
+            self.channels.refresh(&self.db).await?;
+            return Ok(());
+        }
+
+        // Bumping the epoch waits for the old leader's in-flight writes and fences later
+        // ones; the refresh then picks up everything it committed.
+        let epoch = claim_leadership(conn, &self.leadership.node_id).await?;
+        self.channels.refresh(&self.db).await?;
+        self.leadership.promote(epoch);
+        Ok(())
+    }
+}
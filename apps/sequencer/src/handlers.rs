@@ -1,36 +1,126 @@
 use axum::{
-    extract::{Path, State},
-    routing::{get, post},
-    Json, Router,
+    async_trait,
+    extract::{DefaultBodyLimit, FromRequestParts, Path, Query, State},
+    http::{request::Parts, StatusCode},
+    middleware,
+    routing::{delete, get, post},
+    Extension, Json, Router,
 };
 use tracing::info;
 use crate::{
+    auth::{require, Caller, Reader, Role},
     error::AppError,
     model::{
+        BindReceiverRequest,
+        ChannelListQuery,
+        ChannelListResponse,
+        ChannelProofResponse,
+        CloseStatusResponse,
+        ChannelQuery,
+        ChannelSearchQuery,
         ChannelView,
         ChannelsByOwnerResponse,
+        DomainResponse,
         FinalizeChannelRequest,
         FinalizeChannelResponse,
+        MetricsResponse,
+        OwnerCloseRequest,
+        OwnerPortfolioResponse,
+        QuarantinedChannelsResponse,
+        RecipientBalancesResponse,
+        RecipientFinalizeRequest,
+        RecipientFinalizeResponse,
+        ReceiverBindingView,
+        ReceiverBindingsResponse,
+        ReceiverChallengeRequest,
+        ReceiverChallengeResponse,
         PayInChannelRequest,
         PayInChannelResponse,
+        PrepareChannelUpdateRequest,
+        PrepareChannelUpdateResponse,
         SeedChannelRequest,
+        SessionResponse,
+        SiweLoginRequest,
+        SiweNonceRequest,
+        SiweNonceResponse,
+        X402RequirementsRequest,
+        X402SettleResponse,
+        X402VerifyResponse,
     },
     service,
+    ratelimit::{limit_clients, Client, ClientIp},
     service::AppState,
+    session::Session,
+    x402::X402RequirementsResponse,
 };
 
+/// Every route declares the role it needs (see `auth::require`). Public routes are the
+/// ones authorized by the caller's own signatures; the rest need an API key once
+/// `AUTH_ENABLED` is on.
 pub fn router(state: AppState) -> Router {
+    let auth = state.auth.clone();
     Router::new()
-        .route("/health", get(health))
-        .route("/channels/by-owner/:owner", get(list_channels_by_owner))
-        .route("/channel/seed", post(seed_channel))
-        .route("/channel/:id", get(get_channel))
-        .route("/channel/finalize", post(finalize_channel))
-        .route("/validate", post(validate_pay_in_channel))
-        .route("/settle", post(settle))
+        .route("/health", require(Role::Public, &auth, get(health)))
+        .route("/metrics", require(Role::ReadOnly, &auth, get(metrics)))
+        .route("/domain", require(Role::Public, &auth, get(domain)))
+        .route("/auth/siwe/nonce", require(Role::Public, &auth, post(siwe_nonce)))
+        .route("/auth/siwe/login", require(Role::Public, &auth, post(siwe_login)))
+        .route("/channels", require(Role::Wallet, &auth, get(list_channels)))
+        .route("/channels/by-owner/:owner", require(Role::Wallet, &auth, get(list_channels_by_owner)))
+        .route("/channels/quarantined", require(Role::ReadOnly, &auth, get(list_quarantined_channels)))
+        .route("/owners/:owner/portfolio", require(Role::Wallet, &auth, get(owner_portfolio)))
+        .route("/recipients/:address/balances", require(Role::Wallet, &auth, get(recipient_balances)))
+        .route("/channel/seed", require(Role::Admin, &auth, post(seed_channel)))
+        .route("/channel/:id", require(Role::Wallet, &auth, get(get_channel)))
+        .route("/channel/:id/proof", require(Role::Wallet, &auth, get(channel_proof)))
+        .route("/channel/:id/close-status", require(Role::Wallet, &auth, get(close_status)))
+        .route("/channel/:id/prepare", require(Role::Wallet, &auth, post(prepare_channel_update)))
+        .route("/channel/finalize", require(Role::Admin, &auth, post(finalize_channel)))
+        .route("/channel/finalize-request", require(Role::Public, &auth, post(request_finalize)))
+        .route("/channel/close", require(Role::Public, &auth, post(close_channel)))
+        .route("/validate", require(Role::ResourceServer, &auth, post(validate_pay_in_channel)))
+        .route("/settle", require(Role::ResourceServer, &auth, post(settle)))
+        .route("/x402/verify", require(Role::ResourceServer, &auth, post(x402_verify)))
+        .route("/x402/settle", require(Role::ResourceServer, &auth, post(x402_settle)))
+        .route("/x402/requirements", require(Role::ResourceServer, &auth, post(x402_requirements)))
+        .route(
+            "/receivers",
+            require(Role::ResourceServer, &auth, get(list_receivers).post(bind_receiver)),
+        )
+        .route("/receivers/challenge", require(Role::ResourceServer, &auth, post(receiver_challenge)))
+        .route("/receivers/:address", require(Role::ResourceServer, &auth, delete(unbind_receiver)))
+        .layer(middleware::from_fn_with_state(state.limits.clone(), limit_clients))
+        .layer(DefaultBodyLimit::max(state.config.max_body_bytes))
         .with_state(state)
 }
 
+/// Who a channel read is for: the SIWE session or API key the auth middleware attached.
+#[async_trait]
+impl FromRequestParts<AppState> for Reader {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        Reader::resolve(
+            &state.auth,
+            parts.extensions.get::<Caller>(),
+            parts.extensions.get::<Session>(),
+        )
+    }
+}
+
+/// Sender of a payment: the address `limit_clients` resolved and the authenticated key.
+#[async_trait]
+impl FromRequestParts<AppState> for Client {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &AppState) -> Result<Self, Self::Rejection> {
+        Ok(Client {
+            ip: parts.extensions.get::<ClientIp>().map(|ClientIp(ip)| *ip),
+            caller: parts.extensions.get::<Caller>().cloned(),
+        })
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/health",
@@ -40,11 +130,49 @@ pub(crate) async fn health() -> &'static str {
     "ok"
 }
 
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Sequencer runtime metrics", body = MetricsResponse))
+)]
+pub(crate) async fn metrics(State(state): State<AppState>) -> Json<MetricsResponse> {
+    Json(service::metrics(&state))
+}
+
+#[utoipa::path(
+    get,
+    path = "/domain",
+    responses((status = 200, description = "EIP-712 domain user signatures must be made in", body = DomainResponse))
+)]
+pub(crate) async fn domain(State(state): State<AppState>) -> Json<DomainResponse> {
+    Json(service::domain(&state))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channels",
+    params(ChannelSearchQuery),
+    responses(
+        (status = 200, description = "Channels held by this sequencer", body = ChannelListResponse),
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Session wallet is neither the owner nor the recipient filtered on")
+    )
+)]
+pub(crate) async fn list_channels(
+    Query(query): Query<ChannelSearchQuery>,
+    State(state): State<AppState>,
+    reader: Reader,
+) -> Result<Json<ChannelListResponse>, AppError> {
+    let response = service::list_channels(&state, reader, query).await?;
+    Ok(Json(response))
+}
+
 #[utoipa::path(
     get,
     path = "/channels/by-owner/{owner}",
     params(
-        ("owner" = String, Path, description = "Owner address (0x...)")
+        ("owner" = String, Path, description = "Owner address (0x...)"),
+        ChannelListQuery
     ),
     responses(
         (status = 200, description = "Channels for owner (on-chain)", body = ChannelsByOwnerResponse),
@@ -53,9 +181,61 @@ pub(crate) async fn health() -> &'static str {
 )]
 pub(crate) async fn list_channels_by_owner(
     Path(owner): Path<String>,
+    Query(query): Query<ChannelListQuery>,
     State(state): State<AppState>,
+    reader: Reader,
 ) -> Result<Json<ChannelsByOwnerResponse>, AppError> {
-    let response = service::list_channels_by_owner(&state, owner).await?;
+    let response = service::list_channels_by_owner(&state, reader, owner, query.status).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/owners/{owner}/portfolio",
+    params(("owner" = String, Path, description = "Owner address (0x...)")),
+    responses(
+        (status = 200, description = "On-chain channels joined with sequencer state", body = OwnerPortfolioResponse),
+        (status = 400, description = "Bad request")
+    )
+)]
+pub(crate) async fn owner_portfolio(
+    Path(owner): Path<String>,
+    State(state): State<AppState>,
+    reader: Reader,
+) -> Result<Json<OwnerPortfolioResponse>, AppError> {
+    let response = service::owner_portfolio(&state, reader, owner).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/recipients/{address}/balances",
+    params(("address" = String, Path, description = "Recipient address (0x...)")),
+    responses(
+        (status = 200, description = "Balances owed to the recipient by channels not yet closed", body = RecipientBalancesResponse),
+        (status = 400, description = "Bad request")
+    )
+)]
+pub(crate) async fn recipient_balances(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+    reader: Reader,
+) -> Result<Json<RecipientBalancesResponse>, AppError> {
+    let response = service::recipient_balances(&state, reader, address).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channels/quarantined",
+    responses(
+        (status = 200, description = "Channels taken out of service by integrity verification", body = QuarantinedChannelsResponse)
+    )
+)]
+pub(crate) async fn list_quarantined_channels(
+    State(state): State<AppState>,
+) -> Result<Json<QuarantinedChannelsResponse>, AppError> {
+    let response = service::list_quarantined_channels(&state).await?;
     Ok(Json(response))
 }
 
@@ -65,7 +245,8 @@ pub(crate) async fn list_channels_by_owner(
     request_body = SeedChannelRequest,
     responses(
         (status = 200, description = "Seeded channel", body = ChannelView),
-        (status = 400, description = "Bad request")
+        (status = 400, description = "Bad request"),
+        (status = 409, description = "Channel is finalizing, closed, archived or already holds co-signed payments")
     )
 )]
 pub(crate) async fn seed_channel(
@@ -87,18 +268,64 @@ pub(crate) async fn seed_channel(
     get,
     path = "/channel/{id}",
     params(
-        ("id" = String, Path, description = "Channel id (0x...)")
+        ("id" = String, Path, description = "Channel id (0x...)"),
+        ChannelQuery
     ),
     responses(
         (status = 200, description = "Channel state", body = ChannelView),
-        (status = 404, description = "Not found")
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel is quarantined")
     )
 )]
 pub(crate) async fn get_channel(
     Path(channel_id): Path<String>,
+    Query(query): Query<ChannelQuery>,
     State(state): State<AppState>,
+    reader: Reader,
 ) -> Result<Json<ChannelView>, AppError> {
-    let response = service::get_channel(&state, channel_id).await?;
+    let response = service::get_channel(&state, reader, channel_id, query.include_archived.unwrap_or(false)).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/{id}/proof",
+    params(("id" = String, Path, description = "Channel id (0x...)")),
+    responses(
+        (status = 200, description = "Verified publishIntermediateChannelState bundle", body = ChannelProofResponse),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel has no co-signed state or is quarantined")
+    )
+)]
+pub(crate) async fn channel_proof(
+    Path(channel_id): Path<String>,
+    State(state): State<AppState>,
+    reader: Reader,
+) -> Result<Json<ChannelProofResponse>, AppError> {
+    let response = service::channel_proof(&state, reader, channel_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/{id}/prepare",
+    params(("id" = String, Path, description = "Channel id (0x...)")),
+    request_body = PrepareChannelUpdateRequest,
+    responses(
+        (status = 200, description = "Next channel state and EIP-712 typed data to sign", body = PrepareChannelUpdateResponse),
+        (status = 400, description = "Bad request"),
+        (status = 403, description = "Session wallet is not the channel owner"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel does not accept settles")
+    )
+)]
+pub(crate) async fn prepare_channel_update(
+    Path(channel_id): Path<String>,
+    State(state): State<AppState>,
+    reader: Reader,
+    Json(payload): Json<PrepareChannelUpdateRequest>,
+) -> Result<Json<PrepareChannelUpdateResponse>, AppError> {
+    let response = service::prepare_channel_update(&state, reader, channel_id, payload).await?;
     Ok(Json(response))
 }
 
@@ -109,11 +336,14 @@ pub(crate) async fn get_channel(
     responses(
         (status = 200, description = "Validated channel update (no state change)", body = PayInChannelResponse),
         (status = 400, description = "Bad request"),
-        (status = 404, description = "Not found")
+        (status = 403, description = "Receiver or fee destination not bound to the caller's api key"),
+        (status = 404, description = "Not found"),
+        (status = 429, description = "Rate limit exceeded or client banned; see Retry-After")
     )
 )]
 pub(crate) async fn validate_pay_in_channel(
     State(state): State<AppState>,
+    client: Client,
     Json(payload): Json<PayInChannelRequest>,
 ) -> Result<Json<PayInChannelResponse>, AppError> {
     info!(
@@ -121,7 +351,7 @@ pub(crate) async fn validate_pay_in_channel(
         sequence_number = payload.sequence_number,
         "validate request"
     );
-    let response = service::validate_pay_in_channel(&state, payload).await?;
+    let response = service::validate_as(&state, &client, payload).await?;
     Ok(Json(response))
 }
 
@@ -130,7 +360,7 @@ pub(crate) async fn validate_pay_in_channel(
     path = "/channel/finalize",
     request_body = FinalizeChannelRequest,
     responses(
-        (status = 200, description = "Finalized channel (on-chain)", body = FinalizeChannelResponse),
+        (status = 200, description = "Close transaction sent; the channel closes once it is mined", body = FinalizeChannelResponse),
         (status = 400, description = "Bad request"),
         (status = 404, description = "Not found")
     )
@@ -144,6 +374,66 @@ pub(crate) async fn finalize_channel(
     Ok(Json(response))
 }
 
+#[utoipa::path(
+    post,
+    path = "/channel/close",
+    request_body = OwnerCloseRequest,
+    responses(
+        (status = 200, description = "Close accepted; the transaction was sent or will be retried", body = CloseStatusResponse),
+        (status = 400, description = "Bad request or not signed by the owner"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel cannot be finalized, moved past the signed sequence, or the request was already accepted")
+    )
+)]
+pub(crate) async fn close_channel(
+    State(state): State<AppState>,
+    Json(payload): Json<OwnerCloseRequest>,
+) -> Result<Json<CloseStatusResponse>, AppError> {
+    info!(channel_id = %payload.channel_id, sequence_number = payload.sequence_number, "owner close request");
+    let response = service::close_channel(&state, payload).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/channel/{id}/close-status",
+    params(("id" = String, Path, description = "Channel id (0x...)")),
+    responses(
+        (status = 200, description = "Close transaction and its on-chain status", body = CloseStatusResponse),
+        (status = 403, description = "Session wallet is neither the owner nor a recipient of this channel"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel is quarantined")
+    )
+)]
+pub(crate) async fn close_status(
+    Path(channel_id): Path<String>,
+    State(state): State<AppState>,
+    reader: Reader,
+) -> Result<Json<CloseStatusResponse>, AppError> {
+    let response = service::close_status(&state, reader, channel_id).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/channel/finalize-request",
+    request_body = RecipientFinalizeRequest,
+    responses(
+        (status = 200, description = "Finalize request queued for the next batch", body = RecipientFinalizeResponse),
+        (status = 400, description = "Bad request, invalid signature or below the minimum owed amount"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "Channel cannot be finalized or the recipient is in cooldown")
+    )
+)]
+pub(crate) async fn request_finalize(
+    State(state): State<AppState>,
+    Json(payload): Json<RecipientFinalizeRequest>,
+) -> Result<Json<RecipientFinalizeResponse>, AppError> {
+    info!(channel_id = %payload.channel_id, recipient = %payload.recipient, "recipient finalize request");
+    let response = service::request_finalize(&state, payload).await?;
+    Ok(Json(response))
+}
+
 #[utoipa::path(
     post,
     path = "/settle",
@@ -151,11 +441,14 @@ pub(crate) async fn finalize_channel(
     responses(
         (status = 200, description = "Accepted channel update (state persisted)", body = PayInChannelResponse),
         (status = 400, description = "Bad request"),
-        (status = 404, description = "Not found")
+        (status = 403, description = "Receiver or fee destination not bound to the caller's api key"),
+        (status = 404, description = "Not found"),
+        (status = 429, description = "Rate limit exceeded or client banned; see Retry-After")
     )
 )]
 pub(crate) async fn settle(
     State(state): State<AppState>,
+    client: Client,
     Json(payload): Json<PayInChannelRequest>,
 ) -> Result<Json<PayInChannelResponse>, AppError> {
     info!(
@@ -163,6 +456,174 @@ pub(crate) async fn settle(
         sequence_number = payload.sequence_number,
         "settle request"
     );
-    let response = service::settle(&state, payload).await?;
+    let response = service::settle_as(&state, &client, payload).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/x402/verify",
+    request_body = X402Request,
+    responses(
+        (status = 200, description = "x402 verify result; rejected payments have isValid false", body = X402VerifyResponse),
+        (status = 403, description = "payTo or fee destination not bound to the caller's api key"),
+        (status = 429, description = "Rate limit exceeded or client banned; see Retry-After")
+    )
+)]
+pub(crate) async fn x402_verify(
+    State(state): State<AppState>,
+    client: Client,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<X402VerifyResponse>, AppError> {
+    info!(x402_version = %body["x402Version"], "x402 verify request");
+    let response = service::x402_verify(&state, &client, body).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/x402/settle",
+    request_body = X402Request,
+    responses(
+        (status = 200, description = "x402 settle result; rejected payments have success false", body = X402SettleResponse),
+        (status = 403, description = "payTo or fee destination not bound to the caller's api key"),
+        (status = 429, description = "Rate limit exceeded or client banned; see Retry-After")
+    )
+)]
+pub(crate) async fn x402_settle(
+    State(state): State<AppState>,
+    client: Client,
+    Json(body): Json<serde_json::Value>,
+) -> Result<Json<X402SettleResponse>, AppError> {
+    info!(x402_version = %body["x402Version"], "x402 settle request");
+    let response = service::x402_settle(&state, &client, body).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/x402/requirements",
+    request_body = X402RequirementsRequest,
+    responses(
+        (status = 200, description = "x402 accepts entry (v1) or PaymentRequired body (v2) for the cpc scheme", body = X402RequirementsResponse),
+        (status = 400, description = "Bad request"),
+        (status = 404, description = "Channel not found, or no channel of the owner can cover the price"),
+        (status = 409, description = "Channel cannot take a payment of this price")
+    )
+)]
+pub(crate) async fn x402_requirements(
+    State(state): State<AppState>,
+    Json(request): Json<X402RequirementsRequest>,
+) -> Result<Json<X402RequirementsResponse>, AppError> {
+    let response = service::x402_requirements(&state, request).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/receivers/challenge",
+    request_body = ReceiverChallengeRequest,
+    responses(
+        (status = 200, description = "ReceiverBinding for the receiver to sign", body = ReceiverChallengeResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "No api key")
+    )
+)]
+pub(crate) async fn receiver_challenge(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<ReceiverChallengeRequest>,
+) -> Result<Json<ReceiverChallengeResponse>, AppError> {
+    let response = service::receiver_challenge(&state, caller.as_deref(), request).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/receivers",
+    request_body = BindReceiverRequest,
+    responses(
+        (status = 200, description = "Receiver bound to the caller's api key", body = ReceiverBindingView),
+        (status = 400, description = "Bad request, expired challenge or invalid receiver signature"),
+        (status = 401, description = "No api key"),
+        (status = 404, description = "Challenge not found or already used")
+    )
+)]
+pub(crate) async fn bind_receiver(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+    Json(request): Json<BindReceiverRequest>,
+) -> Result<Json<ReceiverBindingView>, AppError> {
+    let response = service::bind_receiver(&state, caller.as_deref(), request).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    get,
+    path = "/receivers",
+    responses(
+        (status = 200, description = "Receivers bound to the caller's api key", body = ReceiverBindingsResponse),
+        (status = 401, description = "No api key")
+    )
+)]
+pub(crate) async fn list_receivers(
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+) -> Result<Json<ReceiverBindingsResponse>, AppError> {
+    let response = service::receiver_bindings(&state, caller.as_deref()).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/receivers/{address}",
+    params(("address" = String, Path, description = "Receiver address (0x...)")),
+    responses(
+        (status = 204, description = "Binding removed"),
+        (status = 401, description = "No api key"),
+        (status = 404, description = "Receiver is not bound to the caller's api key")
+    )
+)]
+pub(crate) async fn unbind_receiver(
+    Path(address): Path<String>,
+    State(state): State<AppState>,
+    caller: Option<Extension<Caller>>,
+) -> Result<StatusCode, AppError> {
+    service::unbind_receiver(&state, caller.as_deref(), address).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/siwe/nonce",
+    request_body = SiweNonceRequest,
+    responses(
+        (status = 200, description = "EIP-4361 message to sign", body = SiweNonceResponse),
+        (status = 400, description = "Bad request or authentication disabled")
+    )
+)]
+pub(crate) async fn siwe_nonce(
+    State(state): State<AppState>,
+    Json(request): Json<SiweNonceRequest>,
+) -> Result<Json<SiweNonceResponse>, AppError> {
+    let response = service::siwe_nonce(&state, request).await?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/siwe/login",
+    request_body = SiweLoginRequest,
+    responses(
+        (status = 200, description = "Session token bound to the signing wallet", body = SessionResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Unknown, used or expired nonce, or wrong signer")
+    )
+)]
+pub(crate) async fn siwe_login(
+    State(state): State<AppState>,
+    Json(request): Json<SiweLoginRequest>,
+) -> Result<Json<SessionResponse>, AppError> {
+    let response = service::siwe_login(&state, request).await?;
     Ok(Json(response))
 }
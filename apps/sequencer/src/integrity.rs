@@ -0,0 +1,258 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethers_core::types::{Address, U256};
+use sqlx::PgPool;
+use tracing::{error, info, warn};
+
+use crate::{
+    cache::ChannelCache,
+    config::Config,
+    crypto::recover_signature,
+    db::{quarantine_channel, scan_channels, WriteError},
+    error::AppError,
+    ha::Leadership,
+    model::{ChannelState, RecipientBalance},
+};
+
+const SCAN_PAGE_SIZE: usize = 500;
+
+/// Checks that a stored channel state is one the sequencer could have produced.
+#[derive(Debug, Clone)]
+pub struct ChannelVerifier {
+    chain_id: u64,
+    channel_manager: Address,
+    max_recipients: usize,
+    sequencer: Address,
+}
+
+impl ChannelVerifier {
+    pub fn new(config: &Config, sequencer: Address) -> Self {
+        Self {
+            chain_id: config.chain_id,
+            channel_manager: config.channel_manager,
+            max_recipients: config.max_recipients,
+            sequencer,
+        }
+    }
+
+    /// Re-derives the EIP-712 digest of the state and checks that the user signature
+    /// recovers to the owner, the sequencer signature to the sequencer, and that the
+    /// recipients pass the same limits as a settle. A freshly seeded state (sequence 0) has
+    /// no recipients and no sequencer signature, and at most the owner's `openChannel`
+    /// signature.
+    pub fn verify(&self, channel: &ChannelState) -> Result<(), String> {
+        if channel.owner.is_zero() {
+            return Err("owner is the zero address".to_string());
+        }
+        check_recipients(&channel.recipients, channel.balance, self.max_recipients).map_err(|err| err.to_string())?;
+        if channel.signature_timestamp > channel.expiry_ts {
+            return Err("signature timestamp is after channel expiry".to_string());
+        }
+
+        if channel.sequence_number == 0 {
+            if !channel.recipients.is_empty() {
+                return Err("unsigned state has recipients".to_string());
+            }
+            if !channel.sequencer_signature.is_empty() {
+                return Err("unsigned state has a sequencer signature".to_string());
+            }
+            if channel.user_signature.is_empty() {
+                return Ok(());
+            }
+            return self.check_signer(channel, &channel.user_signature, channel.owner, "opening");
+        }
+
+        self.check_signer(channel, &channel.user_signature, channel.owner, "user")?;
+        self.check_signer(channel, &channel.sequencer_signature, self.sequencer, "sequencer")
+    }
+
+    fn check_signer(&self, channel: &ChannelState, signature: &str, expected: Address, role: &str) -> Result<(), String> {
+        if signature.is_empty() {
+            return Err(format!("{role} signature is missing"));
+        }
+        let recovered = recover_signature(
+            channel.channel_id,
+            channel.sequence_number,
+            channel.signature_timestamp,
+            &channel.recipients,
+            self.chain_id,
+            self.channel_manager,
+            signature,
+        )
+        .map_err(|err| format!("{role} signature is invalid: {err}"))?;
+        if recovered != expected {
+            return Err(format!(
+                "{role} signature recovers to 0x{recovered:x}, expected 0x{expected:x}"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Limits every channel state must respect, whether it comes from a settle or from storage.
+pub fn check_recipients(recipients: &[RecipientBalance], balance: U256, max_recipients: usize) -> Result<(), AppError> {
+    if recipients.len() > max_recipients {
+        return Err(AppError::bad_request("max recipients exceeded"));
+    }
+
+    let total = recipients
+        .iter()
+        .fold(U256::zero(), |acc, r| acc.saturating_add(r.balance));
+    if total > balance {
+        return Err(AppError::bad_request("exceeds channel capacity"));
+    }
+    Ok(())
+}
+
+/// Verifies channels as the cache loads them, so a row is checked before it is served
+/// whether or not the startup scan has reached it. Failures are refused on every node and
+/// quarantined by the leader.
+#[derive(Clone)]
+pub struct LoadVerifier {
+    verifier: ChannelVerifier,
+    leadership: Arc<Leadership>,
+}
+
+impl LoadVerifier {
+    pub fn new(verifier: ChannelVerifier, leadership: Arc<Leadership>) -> Self {
+        Self { verifier, leadership }
+    }
+
+    /// Checks a freshly loaded row. Undecodable rows fail like unverifiable ones.
+    pub async fn check(
+        &self,
+        db: &PgPool,
+        channel_id: &str,
+        loaded: Result<ChannelState, sqlx::Error>,
+    ) -> Result<ChannelState, AppError> {
+        let outcome = match loaded {
+            Ok(channel) => {
+                let verifier = self.verifier.clone();
+                tokio::task::spawn_blocking(move || verifier.verify(&channel).map(|()| channel))
+                    .await
+                    .map_err(|err| {
+                        error!(error = %err, "channel verification task failed");
+                        AppError::Internal
+                    })?
+            }
+            Err(err @ sqlx::Error::ColumnDecode { .. }) => Err(format!("unreadable row: {err}")),
+            Err(err) => return Err(err.into()),
+        };
+        let reason = match outcome {
+            Ok(channel) => return Ok(channel),
+            Err(reason) => reason,
+        };
+
+        error!(channel_id = %channel_id, reason = %reason, "channel failed verification on load");
+        if let Ok(fencing_token) = self.leadership.ensure_leader() {
+            match quarantine_channel(db, channel_id, &reason, now_secs(), fencing_token).await {
+                Ok(()) | Err(WriteError::Fenced(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Err(AppError::conflict(format!("channel failed verification: {reason}")))
+    }
+
+    /// Verifies rows bulk-loaded into the cache, keeping those that pass. Failures are
+    /// left to `check` on their first access.
+    pub async fn retain_valid(&self, channels: Vec<ChannelState>) -> Vec<ChannelState> {
+        let verifier = self.verifier.clone();
+        tokio::task::spawn_blocking(move || {
+            channels
+                .into_iter()
+                .filter(|channel| match verifier.verify(channel) {
+                    Ok(()) => true,
+                    Err(reason) => {
+                        warn!(channel_id = %format!("0x{:x}", channel.channel_id), reason = %reason, "not caching channel that failed verification");
+                        false
+                    }
+                })
+                .collect()
+        })
+        .await
+        .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub scanned: usize,
+    pub quarantined: usize,
+}
+
+/// Verifies every live channel once this node holds leadership (see `scan`). Standbys
+/// wait for promotion instead of scanning, so they never write under a running leader.
+pub struct IntegrityScan {
+    pub leadership: Arc<Leadership>,
+    pub db: PgPool,
+    pub channels: Arc<ChannelCache>,
+    pub verifier: ChannelVerifier,
+    pub poll_interval: Duration,
+}
+
+impl IntegrityScan {
+    pub async fn run(self) {
+        let mut ticker = tokio::time::interval(self.poll_interval);
+        loop {
+            ticker.tick().await;
+            let Ok(fencing_token) = self.leadership.ensure_leader() else {
+                continue;
+            };
+            match scan(&self.db, &self.verifier, &self.channels, fencing_token).await {
+                Ok(_) => return,
+                Err(err) => warn!(error = %err, "channel integrity scan failed; retrying"),
+            }
+        }
+    }
+}
+
+/// Verifies every live channel in Postgres, quarantining rows that fail to parse or
+/// verify so they are reported instead of served. Quarantined channels are dropped from
+/// the cache; a settle still holding one of them is refused by `db::write_channel`.
+pub async fn scan(
+    db: &PgPool,
+    verifier: &ChannelVerifier,
+    channels: &ChannelCache,
+    fencing_token: Option<u64>,
+) -> Result<ScanReport, WriteError> {
+    let mut report = ScanReport::default();
+    let mut after = String::new();
+    loop {
+        let page = scan_channels(db, &after, SCAN_PAGE_SIZE).await?;
+        let Some((last, _)) = page.last() else {
+            break;
+        };
+        after = last.clone();
+        let full_page = page.len() == SCAN_PAGE_SIZE;
+
+        for (channel_id, channel) in page {
+            report.scanned += 1;
+            let outcome = channel
+                .map_err(|err| format!("unreadable row: {err}"))
+                .and_then(|channel| verifier.verify(&channel));
+            if let Err(reason) = outcome {
+                error!(channel_id = %channel_id, reason = %reason, "quarantining channel");
+                quarantine_channel(db, &channel_id, &reason, now_secs(), fencing_token).await?;
+                channels.remove(&channel_id).await;
+                report.quarantined += 1;
+            }
+        }
+
+        if !full_page {
+            break;
+        }
+    }
+
+    info!(scanned = report.scanned, quarantined = report.quarantined, "channel integrity scan finished");
+    Ok(report)
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,21 @@
+pub mod archive;
+pub mod auth;
+pub mod cache;
+pub mod config;
+pub mod crypto;
+pub mod db;
+pub mod error;
+pub mod finalize;
+pub mod ha;
+pub mod handlers;
+pub mod integrity;
+pub mod lifecycle;
+pub mod model;
+pub mod openapi;
+pub mod ratelimit;
+pub mod replica;
+pub mod service;
+pub mod session;
+pub mod snapshot;
+pub mod writer;
+pub mod x402;
@@ -0,0 +1,98 @@
+use crate::{error::AppError, model::ChannelStatus};
+
+/// Something that happens to a channel and may change its status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelEvent {
+    /// Replaces the state with a freshly seeded one. `signed` when the current state holds
+    /// co-signed payments (sequence above 0), which a reseed would erase.
+    Seed { signed: bool },
+    Settle,
+    BeginFinalize,
+    FinalizeSubmitted,
+    FinalizeFailed,
+    Quarantine,
+}
+
+/// The only place that decides which status moves are legal. `from` must be the
+/// effective status (see `ChannelState::effective_status`) so expiry is taken into account.
+pub fn transition(from: ChannelStatus, event: ChannelEvent) -> Result<ChannelStatus, AppError> {
+    use ChannelEvent::*;
+    use ChannelStatus::*;
+
+    match (from, event) {
+        (_, Quarantine) => Ok(Quarantined),
+        (Quarantined, Seed { .. }) => Ok(Open),
+        (Open | Expired, Seed { signed: false }) => Ok(Open),
+        (Open | Expired, Seed { signed: true }) => Err(AppError::conflict(
+            "channel has co-signed payments; reseeding would erase them",
+        )),
+        (Open, Settle) => Ok(Open),
+        (Open | Expired, BeginFinalize) => Ok(Finalizing),
+        (Finalizing, FinalizeSubmitted) => Ok(Closed),
+        (Finalizing, FinalizeFailed) => Ok(Open),
+        (Finalizing, _) => Err(AppError::conflict("channel has a finalize transaction pending")),
+        (Closed, _) => Err(AppError::conflict("channel is closed")),
+        (Expired, _) => Err(AppError::conflict("channel has expired")),
+        (Quarantined, _) => Err(AppError::conflict("channel is quarantined")),
+        (Open, _) => Err(AppError::conflict(format!("channel is open; cannot apply {event:?}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ChannelEvent::*;
+    use ChannelStatus::*;
+
+    const ALL: [ChannelStatus; 5] = [Open, Finalizing, Closed, Expired, Quarantined];
+
+    #[test]
+    fn legal_moves() {
+        let legal = [
+            (Open, Settle, Open),
+            (Open, Seed { signed: false }, Open),
+            (Expired, Seed { signed: false }, Open),
+            (Quarantined, Seed { signed: false }, Open),
+            (Quarantined, Seed { signed: true }, Open),
+            (Open, BeginFinalize, Finalizing),
+            (Expired, BeginFinalize, Finalizing),
+            (Finalizing, FinalizeSubmitted, Closed),
+            (Finalizing, FinalizeFailed, Open),
+        ];
+        for (from, event, to) in legal {
+            assert_eq!(transition(from, event).unwrap(), to, "{from:?} + {event:?}");
+        }
+    }
+
+    #[test]
+    fn quarantine_is_always_allowed() {
+        for from in ALL {
+            assert_eq!(transition(from, Quarantine).unwrap(), Quarantined);
+        }
+    }
+
+    #[test]
+    fn illegal_moves_conflict() {
+        let illegal = [
+            (Open, Seed { signed: true }),
+            (Expired, Seed { signed: true }),
+            (Open, FinalizeSubmitted),
+            (Open, FinalizeFailed),
+            (Expired, Settle),
+            (Finalizing, Settle),
+            (Finalizing, BeginFinalize),
+            (Finalizing, Seed { signed: false }),
+            (Closed, Settle),
+            (Closed, Seed { signed: false }),
+            (Closed, BeginFinalize),
+            (Quarantined, Settle),
+            (Quarantined, BeginFinalize),
+        ];
+        for (from, event) in illegal {
+            assert!(
+                matches!(transition(from, event), Err(AppError::Conflict(_))),
+                "{from:?} + {event:?} should be refused"
+            );
+        }
+    }
+}
@@ -1,49 +1,166 @@
-mod config;
-mod crypto;
-mod db;
-mod error;
-mod handlers;
-mod model;
-mod openapi;
-mod service;
-
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
+use clap::{Parser, Subcommand};
 use dotenvy::dotenv;
 use ethers_providers::{Http, Provider};
 use ethers_signers::{LocalWallet, Signer};
-use sqlx::postgres::PgPoolOptions;
-use tokio::sync::RwLock;
-use tracing::info;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use tracing::{info, warn};
 
-use crate::{
+use cpc_sequencer::{
+    archive::Archiver,
+    auth::{self, Authenticator, Role},
+    cache::ChannelCache,
     config::Config,
-    db::{init_db, load_state},
+    db::{init_db, insert_api_key, list_api_keys, revoke_api_key},
+    finalize::FinalizeBatcher,
+    ha::{Election, Leadership},
     handlers::router,
-    openapi::ApiDoc,
-    service::{fetch_sequencer_address, AppState},
+    integrity::{ChannelVerifier, IntegrityScan, LoadVerifier},
+    openapi::api_doc,
+    ratelimit::RateLimiter,
+    replica::Replicator,
+    service::{fetch_payment_asset, fetch_sequencer_address, resolve_domain, AppState},
+    session::SessionKeys,
+    snapshot::{self, Snapshot},
+    writer::WritePipeline,
 };
-use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+#[derive(Parser)]
+#[command(about = "CPC payment-channel sequencer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP server (default).
+    Serve,
+    /// Write every live and archived channel to a snapshot (`.cbor` for CBOR, JSON otherwise).
+    Export { path: PathBuf },
+    /// Load a snapshot into an empty store.
+    Import { path: PathBuf },
+    /// Re-verify every stored state and print a report; exits non-zero on failures.
+    Verify {
+        /// Verify a snapshot file instead of the database.
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+    },
+    /// Manage the API keys that authenticate requests when `AUTH_ENABLED` is on.
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Create a key and print it; the secret is not shown again.
+    Create {
+        /// admin, resource-server or read-only.
+        #[arg(long)]
+        role: String,
+        #[arg(long, default_value = "")]
+        label: String,
+    },
+    /// List every key with its role and revocation time.
+    List,
+    /// Revoke a key; running sequencers stop accepting it on their next key refresh.
+    Revoke { key_id: String },
+}
+
+type BoxError = Box<dyn std::error::Error>;
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() -> Result<(), BoxError> {
     dotenv().ok();
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    let cli = Cli::parse();
     let config = Arc::new(Config::from_env()?);
-    let port = config.port;
+
+    if let Some(Command::Verify { snapshot: Some(path) }) = &cli.command {
+        return verify(&config, Snapshot::read(path)?);
+    }
 
     let db = PgPoolOptions::new()
         .max_connections(5)
         .connect(&config.database_url)
         .await?;
-
     init_db(&db).await?;
-    let channels = load_state(&db).await?;
 
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config, db).await,
+        Command::Export { path } => {
+            let snapshot = snapshot::export(&db, &config).await?;
+            snapshot.write(&path)?;
+            println!(
+                "exported {} live and {} archived channels to {}",
+                snapshot.channels.len(),
+                snapshot.archived_channels.len(),
+                path.display()
+            );
+            Ok(())
+        }
+        Command::Import { path } => {
+            let imported = snapshot::import(&db, &config, &Snapshot::read(&path)?).await?;
+            println!("imported {imported} channels from {}", path.display());
+            Ok(())
+        }
+        Command::Verify { .. } => verify(&config, snapshot::export(&db, &config).await?),
+        Command::Keys { command } => keys(&db, command).await,
+    }
+}
+
+async fn keys(db: &PgPool, command: KeysCommand) -> Result<(), BoxError> {
+    match command {
+        KeysCommand::Create { role, label } => {
+            let role = Role::parse(&role)?;
+            let (key_id, secret) = auth::generate_key();
+            insert_api_key(db, &key_id, &secret, role.as_str(), &label, auth::now_secs()).await?;
+            println!("created {} key {key_id}", role.as_str());
+            println!("api key: {key_id}.{secret}");
+        }
+        KeysCommand::List => {
+            for (key_id, role, label, created_at, revoked_at) in list_api_keys(db).await? {
+                let revoked = revoked_at.map(|ts| format!(" revoked_at={ts}")).unwrap_or_default();
+                println!("{key_id} role={role} label={label:?} created_at={created_at}{revoked}");
+            }
+        }
+        KeysCommand::Revoke { key_id } => {
+            if !revoke_api_key(db, &key_id, auth::now_secs()).await? {
+                return Err(format!("no active api key {key_id}").into());
+            }
+            println!("revoked {key_id}");
+        }
+    }
+    Ok(())
+}
+
+fn verify(config: &Config, snapshot: Snapshot) -> Result<(), BoxError> {
+    let sequencer = config.sequencer_private_key.parse::<LocalWallet>()?.address();
+    let report = snapshot::verify(&snapshot, &ChannelVerifier::new(config, sequencer));
+    for (channel_id, reason) in &report.failures {
+        println!("FAIL {channel_id}: {reason}");
+    }
+    println!(
+        "checked {} channels: {} failed, {} already quarantined",
+        report.checked,
+        report.failures.len(),
+        report.quarantined
+    );
+    if !report.is_clean() {
+        return Err(format!("{} channels failed verification", report.failures.len()).into());
+    }
+    Ok(())
+}
+
+async fn serve(config: Arc<Config>, db: PgPool) -> Result<(), BoxError> {
+    let port = config.port;
     let provider = Arc::new(Provider::<Http>::try_from(config.rpc_url.as_str())?);
     let sequencer_wallet = config.sequencer_private_key.parse::<LocalWallet>()?.with_chain_id(config.chain_id);
     let sequencer_address = sequencer_wallet.address();
@@ -55,18 +172,155 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .into());
     }
+    let payment_asset = fetch_payment_asset(provider.clone(), config.channel_manager).await?;
+    let domain = resolve_domain(provider.clone(), &config).await?;
+    info!(
+        domain_separator = %domain.domain_separator,
+        verified_on_chain = domain.verified_on_chain,
+        "EIP-712 domain resolved"
+    );
+
+    let leadership = Arc::new(if config.read_replica {
+        Leadership::replica()
+    } else if config.ha_enabled {
+        Leadership::standby(config.ha_node_id.clone())
+    } else {
+        Leadership::standalone()
+    });
+
+    let verifier = ChannelVerifier::new(&config, sequencer_address);
+    let channels = Arc::new(
+        ChannelCache::new(config.channel_cache_capacity)
+            .with_verifier(LoadVerifier::new(verifier.clone(), leadership.clone())),
+    );
+    if config.channel_cache_warmup > 0 {
+        let warmed = channels.warm_up(&db, config.channel_cache_warmup).await?;
+        info!(warmed, "channel cache warmed");
+    }
+
+    let write_pipeline = (config.write_batch_enabled && !config.read_replica).then(|| {
+        info!(
+            max_batch_size = config.write_batch_max_size,
+            max_delay_ms = config.write_batch_max_delay_ms,
+            "group-commit write pipeline enabled"
+        );
+        Arc::new(WritePipeline::spawn(
+            db.clone(),
+            config.write_batch_max_size,
+            Duration::from_millis(config.write_batch_max_delay_ms),
+        ))
+    });
+
+    let mut replication = None;
+    if config.read_replica {
+        let replicator = Replicator::new(config.database_url.clone(), db.clone(), channels.clone());
+        replication = Some(replicator.status());
+        info!("running as read-only replica");
+        tokio::spawn(replicator.run());
+    } else if config.ha_enabled {
+        info!(node_id = %config.ha_node_id, "high availability enabled, starting as standby");
+        tokio::spawn(
+            Election {
+                leadership: leadership.clone(),
+                database_url: config.database_url.clone(),
+                db: db.clone(),
+                channels: channels.clone(),
+                lock_key: config.ha_lock_key,
+                poll_interval: Duration::from_millis(config.ha_poll_interval_ms),
+            }
+            .run(),
+        );
+    }
+
+    // Replicas do not write; they rely on the primary's scan and never load quarantined
+    // rows. Standbys scan once promoted.
+    if config.verify_on_startup && !config.read_replica {
+        tokio::spawn(
+            IntegrityScan {
+                leadership: leadership.clone(),
+                db: db.clone(),
+                channels: channels.clone(),
+                verifier,
+                poll_interval: Duration::from_millis(config.ha_poll_interval_ms),
+            }
+            .run(),
+        );
+    }
+
+    let auth = if config.auth_enabled {
+        let sessions = SessionKeys::new(&config.sequencer_private_key, config.session_ttl_secs);
+        let auth = Arc::new(
+            Authenticator::load(&db, config.auth_replay_window_secs, config.max_body_bytes, sessions).await?,
+        );
+        info!(replay_window_secs = config.auth_replay_window_secs, "api authentication enabled");
+        tokio::spawn(
+            auth.clone()
+                .run_refresh(db.clone(), Duration::from_secs(config.auth_key_refresh_secs.max(1))),
+        );
+        auth
+    } else {
+        warn!("api authentication disabled; admin routes are open to anyone who can reach the port");
+        Arc::new(Authenticator::disabled())
+    };
+
+    let limits = Arc::new(RateLimiter::new(&config));
+    if config.rate_limit_enabled {
+        tokio::spawn(limits.clone().run_cleanup(Duration::from_secs(60)));
+    } else {
+        warn!("rate limiting disabled");
+    }
 
     let state = AppState {
         db,
-        channels: Arc::new(RwLock::new(channels)),
+        channels,
         config,
         provider,
         sequencer_wallet,
+        payment_asset,
+        domain: Arc::new(domain),
+        write_pipeline,
+        leadership,
+        replication,
+        auth,
+        limits,
     };
 
-    let app = router(state).merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()));
+    if !state.config.read_replica {
+        tokio::spawn(
+            FinalizeBatcher {
+                state: state.clone(),
+                interval: Duration::from_secs(state.config.finalize_batch_interval_secs),
+                max_batch_size: state.config.finalize_batch_max_size,
+            }
+            .run(),
+        );
+    }
+
+    if state.config.archive_enabled && !state.config.read_replica {
+        info!(
+            after_secs = state.config.archive_after_secs,
+            retention_days = state.config.archive_retention_days,
+            "channel archival enabled"
+        );
+        tokio::spawn(
+            Archiver {
+                state: state.clone(),
+                interval: Duration::from_secs(state.config.archive_interval_secs),
+                archive_after: Duration::from_secs(state.config.archive_after_secs),
+                retention: (state.config.archive_retention_days > 0)
+                    .then(|| Duration::from_secs(state.config.archive_retention_days * 86_400)),
+            }
+            .run(),
+        );
+    }
+
+    let app = router(state).merge(SwaggerUi::new("/docs").url("/openapi.json", api_doc()));
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     info!("sequencer listening on {}", addr);
-    axum::serve(tokio::net::TcpListener::bind(addr).await?, app).await?;
+    axum::serve(
+        tokio::net::TcpListener::bind(addr).await?,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
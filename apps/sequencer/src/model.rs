@@ -1,6 +1,50 @@
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use ethers_core::types::{Address, H256, U256};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::error::AppError;
+
+/// Lifecycle status of a channel. Moves between statuses go through
+/// `lifecycle::transition`; `Expired` is never stored but derived from `expiry_ts`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelStatus {
+    Open,
+    /// A `finalCloseBySequencer` transaction is being submitted.
+    Finalizing,
+    Closed,
+    Expired,
+    /// Failed integrity verification; never served.
+    Quarantined,
+}
+
+impl ChannelStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelStatus::Open => "open",
+            ChannelStatus::Finalizing => "finalizing",
+            ChannelStatus::Closed => "closed",
+            ChannelStatus::Expired => "expired",
+            ChannelStatus::Quarantined => "quarantined",
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        match input {
+            "open" => Ok(ChannelStatus::Open),
+            "finalizing" => Ok(ChannelStatus::Finalizing),
+            "closed" => Ok(ChannelStatus::Closed),
+            "expired" => Ok(ChannelStatus::Expired),
+            "quarantined" => Ok(ChannelStatus::Quarantined),
+            other => Err(AppError::bad_request(format!("invalid channel status: {other}"))),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ChannelState {
@@ -13,6 +57,25 @@ pub struct ChannelState {
     pub sequencer_signature: String,
     pub signature_timestamp: u64,
     pub recipients: Vec<RecipientBalance>,
+    pub status: ChannelStatus,
+    /// Hash of the `finalCloseBySequencer` transaction, once the channel was finalized.
+    pub close_tx_hash: Option<String>,
+    pub closed_at: Option<u64>,
+}
+
+impl ChannelState {
+    /// The stored status, except that an open channel past its expiry is `Expired`.
+    pub fn effective_status(&self) -> ChannelStatus {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        if self.status == ChannelStatus::Open && now > self.expiry_ts {
+            ChannelStatus::Expired
+        } else {
+            self.status
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -22,6 +85,42 @@ pub struct RecipientBalance {
     pub position: i32,
 }
 
+/// A stored channel row exactly as persisted, used by snapshots. Values are kept raw so
+/// that even rows that no longer parse survive an export/import round trip.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelRecord {
+    pub channel_id: String,
+    pub owner: String,
+    pub balance: String,
+    pub expiry_ts: i64,
+    pub sequence_number: i64,
+    pub user_signature: String,
+    pub sequencer_signature: String,
+    pub signature_timestamp: i64,
+    #[serde(default = "default_record_status")]
+    pub status: String,
+    pub close_tx_hash: Option<String>,
+    pub closed_at: Option<i64>,
+    pub quarantine_reason: Option<String>,
+    pub quarantined_at: Option<i64>,
+    /// Set on channels exported from the archive tables.
+    pub archived_at: Option<i64>,
+    pub recipients: Vec<RecipientRecord>,
+}
+
+fn default_record_status() -> String {
+    ChannelStatus::Open.as_str().to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientRecord {
+    pub recipient_address: String,
+    pub balance: String,
+    pub position: i32,
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SeedChannelRequest {
@@ -29,16 +128,21 @@ pub struct SeedChannelRequest {
     pub owner: String,
     pub balance: String,
     pub expiry_timestamp: u64,
+    /// The `userSignature` passed to `openChannel`: the owner's signature over the
+    /// sequence-0 `ChannelData` with no recipients. Lets an unused channel be finalized.
+    pub user_signature: Option<String>,
+    /// The `signatureTimestamp` passed to `openChannel`; required with `user_signature`.
+    pub signature_timestamp: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FeeForPayment {
     pub fee_destination_address: String,
     pub fee_amount_curds: String,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PayInChannelRequest {
     pub channel_id: String,
@@ -51,12 +155,263 @@ pub struct PayInChannelRequest {
     pub fee_for_payment: Option<FeeForPayment>,
 }
 
+/// x402 v1 facilitator request body for `/x402/verify` and `/x402/settle`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402Request {
+    pub x402_version: u32,
+    pub payment_payload: PaymentPayload,
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// Decoded `X-PAYMENT` header; for the `cpc` scheme the payload is a channel update.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentPayload {
+    pub x402_version: u32,
+    pub scheme: String,
+    /// `eip155:<chainId>`.
+    pub network: String,
+    pub payload: PayInChannelRequest,
+}
+
+/// One entry of a resource server's `accepts` list (see `docs/x402-eip155-cpc-schema.md`).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirements {
+    pub scheme: String,
+    pub network: String,
+    /// Minimum payment, in token base units.
+    pub max_amount_required: String,
+    pub resource: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: String,
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    /// Token contract the channels are funded with.
+    pub asset: String,
+    /// Scheme-specific channel metadata.
+    #[schema(value_type = Option<Object>)]
+    pub extra: Option<serde_json::Value>,
+}
+
+/// Input of `/x402/requirements`. With neither `channel_id` nor `owner` the requirements
+/// carry no channel, and the client picks one of its own.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402RequirementsRequest {
+    pub pay_to: String,
+    /// Price in token base units; becomes `maxAmountRequired`.
+    pub price: String,
+    pub resource: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+    pub max_timeout_seconds: Option<u64>,
+    #[schema(value_type = Option<Object>)]
+    pub output_schema: Option<serde_json::Value>,
+    /// Payer to pick a channel for; ignored when `channel_id` is given.
+    pub owner: Option<String>,
+    /// Channel the payment must use.
+    pub channel_id: Option<String>,
+    /// `2` returns a v2 `PaymentRequired` body (needs the `x402-v2` feature); default 1.
+    pub x402_version: Option<u64>,
+}
+
+/// `PaymentRequirements.extra` of the `cpc` scheme.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CpcRequirementsExtra {
+    pub channel_id: Option<String>,
+    pub next_sequence_number: Option<u64>,
+    pub channel_expiry: Option<u64>,
+    pub channel_manager: String,
+    pub domain: TypedDataDomain,
+    /// How far ahead of the server clock a signature timestamp may be.
+    pub timestamp_skew_seconds: u64,
+    pub max_recipients: usize,
+    /// When set, payments may carry a `feeForPayment` to this address.
+    pub fee_destination_address: Option<String>,
+}
+
+/// EIP-712 domain every channel signature is made in.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedDataDomain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ChannelQuery {
+    /// Also look up channels that were moved to the archive.
+    pub include_archived: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ChannelListQuery {
+    /// Only channels with this lifecycle status.
+    pub status: Option<ChannelStatus>,
+}
+
+/// Sort key for `GET /channels`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ChannelSort {
+    ChannelId,
+    Expiry,
+    #[default]
+    UpdatedAt,
+    Remaining,
+}
+
+impl ChannelSort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelSort::ChannelId => "channelId",
+            ChannelSort::Expiry => "expiry",
+            ChannelSort::UpdatedAt => "updatedAt",
+            ChannelSort::Remaining => "remaining",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ChannelSearchQuery {
+    /// Only channels owned by this address.
+    pub owner: Option<String>,
+    /// Only channels that pay this address.
+    pub recipient: Option<String>,
+    /// Only channels with this lifecycle status.
+    pub status: Option<ChannelStatus>,
+    /// Only channels expiring at or after this unix time.
+    pub expires_after: Option<u64>,
+    /// Only channels expiring at or before this unix time.
+    pub expires_before: Option<u64>,
+    /// Only channels with at least this much unallocated balance (decimal uint256).
+    pub min_remaining: Option<String>,
+    /// Only channels written at or after this unix time.
+    pub updated_since: Option<u64>,
+    /// Defaults to `updatedAt`.
+    pub sort: Option<ChannelSort>,
+    /// Defaults to `desc`.
+    pub order: Option<SortOrder>,
+    /// Page size, at most 500 (default 50).
+    pub limit: Option<u32>,
+    /// `nextCursor` from the previous page; must be used with the same sort and order.
+    pub cursor: Option<String>,
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FinalizeChannelRequest {
     pub channel_id: String,
 }
 
+/// An owner closing a channel early at its latest co-signed state.
+///
+/// `signature` is the owner's EIP-712 signature over
+/// `CloseRequest(bytes32 channelId,uint256 sequenceNumber,uint256 timestamp)` in the channel
+/// manager's domain; `sequenceNumber` must be the channel's current sequence.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerCloseRequest {
+    pub channel_id: String,
+    pub sequence_number: u64,
+    pub timestamp: u64,
+    pub signature: String,
+}
+
+/// On-chain progress of a channel's close transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionStatus {
+    /// No close transaction was sent for this channel.
+    None,
+    Pending,
+    Confirmed,
+    Reverted,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseStatusResponse {
+    pub channel_id: String,
+    pub status: ChannelStatus,
+    pub transaction_hash: Option<String>,
+    pub transaction_status: TransactionStatus,
+    pub block_number: Option<u64>,
+}
+
+/// A recipient asking the sequencer to close a channel that owes it.
+///
+/// `signature` is the recipient's EIP-712 signature over
+/// `FinalizeRequest(bytes32 channelId,address recipient,uint256 timestamp,string reason)`
+/// in the channel manager's domain, with `reason` signed as `""` when omitted.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientFinalizeRequest {
+    pub channel_id: String,
+    pub recipient: String,
+    pub timestamp: u64,
+    pub reason: Option<String>,
+    pub signature: String,
+}
+
+/// Progress of a recipient's finalize request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalizeRequestStatus {
+    /// Waiting for the next finalize batch.
+    Pending,
+    /// A close transaction was sent and is waiting for its receipt.
+    Submitted,
+    /// The channel closed on-chain.
+    Confirmed,
+    Failed,
+}
+
+impl FinalizeRequestStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FinalizeRequestStatus::Pending => "pending",
+            FinalizeRequestStatus::Submitted => "submitted",
+            FinalizeRequestStatus::Confirmed => "confirmed",
+            FinalizeRequestStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientFinalizeResponse {
+    pub request_id: i64,
+    pub channel_id: String,
+    /// Balance owed to the recipient in the latest co-signed state.
+    pub owed: String,
+    /// Always `pending`; the channel is closed with the next finalize batch.
+    pub status: FinalizeRequestStatus,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ChannelView {
@@ -69,6 +424,14 @@ pub struct ChannelView {
     pub sequencer_signature: String,
     pub signature_timestamp: u64,
     pub recipients: Vec<RecipientView>,
+    pub status: ChannelStatus,
+    pub close_tx_hash: Option<String>,
+    pub closed_at: Option<u64>,
+    /// Whether the channel was served from the archive tables.
+    pub archived: bool,
+    /// Set for recipients reading a channel they do not own: `recipients` holds only
+    /// their own entry and the signatures are blank.
+    pub redacted: bool,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -90,6 +453,57 @@ pub struct FinalizeChannelResponse {
     pub transaction_hash: String,
 }
 
+/// A payment to prepare the signature for; same fields as the settle it precedes.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareChannelUpdateRequest {
+    pub amount: String,
+    pub receiver: String,
+    pub fee_for_payment: Option<FeeForPayment>,
+}
+
+/// The next channel state as the sequencer will check it, ready to sign.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrepareChannelUpdateResponse {
+    pub channel_id: String,
+    pub sequence_number: u64,
+    /// Server time; any timestamp up to the channel expiry and not too far ahead works.
+    pub timestamp: u64,
+    /// Recipients in signing order; `amounts[i]` belongs to `recipients[i]`.
+    pub recipients: Vec<String>,
+    pub amounts: Vec<String>,
+    /// EIP-712 digest of the `ChannelData` to sign.
+    pub digest: String,
+    /// `eth_signTypedData_v4` payload for the same `ChannelData`.
+    #[schema(value_type = Object)]
+    pub typed_data: serde_json::Value,
+}
+
+/// Everything needed to submit the latest co-signed state with
+/// `publishIntermediateChannelState`, or to check it independently.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelProofResponse {
+    pub channel_id: String,
+    pub chain_id: u64,
+    /// Channel manager contract the calldata is meant for.
+    pub channel_manager: String,
+    pub status: ChannelStatus,
+    pub expiry_timestamp: u64,
+    pub sequence_number: u64,
+    pub timestamp: u64,
+    /// Recipients in signing order; `amounts[i]` belongs to `recipients[i]`.
+    pub recipients: Vec<String>,
+    pub amounts: Vec<String>,
+    pub user_signature: String,
+    pub sequencer_signature: String,
+    /// EIP-712 digest both signatures were made over.
+    pub digest: String,
+    /// ABI-encoded `publishIntermediateChannelState` call.
+    pub calldata: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ChannelsByOwnerResponse {
@@ -97,6 +511,327 @@ pub struct ChannelsByOwnerResponse {
     pub channel_ids: Vec<String>,
 }
 
+/// One channel of an owner's portfolio. Amounts, expiry and sequence are only known for
+/// channels the sequencer holds.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PortfolioChannel {
+    pub channel_id: String,
+    /// Listed by the channel manager contract for this owner.
+    pub on_chain: bool,
+    /// Stored by the sequencer (live, quarantined or archived).
+    pub in_sequencer: bool,
+    pub archived: bool,
+    pub status: Option<ChannelStatus>,
+    pub capacity: Option<String>,
+    pub spent: Option<String>,
+    pub remaining: Option<String>,
+    pub expiry_timestamp: Option<u64>,
+    pub next_sequence_number: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OwnerPortfolioResponse {
+    pub owner: String,
+    pub channels: Vec<PortfolioChannel>,
+    /// Sum over live channels the sequencer holds.
+    pub total_capacity: String,
+    pub total_spent: String,
+    /// Remaining capacity of open channels only, i.e. what the owner can still spend.
+    pub total_remaining: String,
+}
+
+/// What one channel owes a recipient as of its latest co-signed state.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientChannelBalance {
+    pub channel_id: String,
+    pub owner: String,
+    pub balance: String,
+    pub status: ChannelStatus,
+    /// Sequence number of the latest co-signed state.
+    pub sequence_number: u64,
+    pub signature_timestamp: u64,
+    pub expiry_timestamp: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientBalancesResponse {
+    pub recipient: String,
+    /// Soonest expiry first.
+    pub channels: Vec<RecipientChannelBalance>,
+    pub total_balance: String,
+}
+
+/// Compact view of a stored channel, without signatures or per-recipient balances.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelSummary {
+    pub channel_id: String,
+    pub owner: String,
+    pub balance: String,
+    /// Balance not yet allocated to any recipient.
+    pub remaining_capacity: String,
+    pub expiry_timestamp: u64,
+    pub status: ChannelStatus,
+    pub sequence_number: u64,
+    /// Sequence number the next settle must carry.
+    pub next_sequence_number: u64,
+    pub recipient_count: u64,
+    /// When the sequencer last wrote this channel (unix seconds).
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelListResponse {
+    pub channels: Vec<ChannelSummary>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402VerifyResponse {
+    pub is_valid: bool,
+    /// The reason message in v1, a reason code in v2.
+    pub invalid_reason: Option<String>,
+    /// v2 only: the message behind `invalid_reason`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invalid_message: Option<String>,
+    /// Channel owner, when the channel is known.
+    pub payer: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402SettleResponse {
+    pub success: bool,
+    /// The reason message in v1, a reason code in v2.
+    pub error_reason: Option<String>,
+    /// v2 only: the message behind `error_reason`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_message: Option<String>,
+    pub payer: Option<String>,
+    /// Settlement is off-chain: this is the sequencer co-signature of the accepted state.
+    pub transaction: String,
+    pub network: String,
+    pub channel_id: Option<String>,
+    pub sequence_number: Option<u64>,
+    /// Base64 JSON of this response without these last two fields.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub payment_response: String,
+    /// Header to return `payment_response` in: `X-PAYMENT-RESPONSE` (v1) or `PAYMENT-RESPONSE` (v2).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub payment_response_header: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedChannel {
+    pub channel_id: String,
+    /// Why the stored state failed integrity verification.
+    pub reason: String,
+    pub quarantined_at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuarantinedChannelsResponse {
+    pub channels: Vec<QuarantinedChannel>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheMetrics {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub warmed: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WritePipelineMetrics {
+    pub max_batch_size: usize,
+    pub max_delay_ms: u64,
+    pub batches: u64,
+    pub writes: u64,
+    pub failed_batches: u64,
+    pub largest_batch: u64,
+    pub avg_batch_size: f64,
+    /// Average time a settle waited in the queue before its batch started committing.
+    pub avg_queue_wait_ms: f64,
+    pub avg_commit_ms: f64,
+    pub last_commit_ms: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeadershipMetrics {
+    pub ha_enabled: bool,
+    /// `leader`, `standby` or `replica`.
+    pub role: String,
+    pub node_id: String,
+    /// Fencing token of the current (or last) leadership term.
+    pub epoch: u64,
+    /// Node id of the current leader as last observed.
+    pub leader: String,
+    pub transitions: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationMetrics {
+    /// Whether the `LISTEN` connection to the primary's database is up.
+    pub connected: bool,
+    pub applied: u64,
+    pub resyncs: u64,
+    pub last_sequence_number: u64,
+    /// Commit time (unix ms) of the last applied update.
+    pub last_committed_at_ms: i64,
+    /// Delay between commit on the primary and apply on this replica, for the last update.
+    pub last_lag_ms: i64,
+    pub since_last_update_ms: i64,
+}
+
+/// The EIP-712 domain resolved at startup.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainResponse {
+    pub domain: TypedDataDomain,
+    /// Hash of `domain`; every signed digest commits to it.
+    pub domain_separator: String,
+    /// Whether the channel manager confirmed the domain through `eip712Domain()` (EIP-5267).
+    pub verified_on_chain: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiverChallengeRequest {
+    pub receiver: String,
+}
+
+/// Challenge a receiver signs to bind itself to the calling API key.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiverChallengeResponse {
+    pub receiver: String,
+    pub key_id: String,
+    pub nonce: String,
+    pub expires_at: u64,
+    /// `eth_signTypedData_v4` payload of the `ReceiverBinding` to sign with the receiver key.
+    #[schema(value_type = Object)]
+    pub typed_data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BindReceiverRequest {
+    pub receiver: String,
+    pub nonce: String,
+    /// Receiver's signature of the challenge's `typedData`.
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiverBindingView {
+    pub receiver: String,
+    pub bound_at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiverBindingsResponse {
+    pub key_id: String,
+    pub receivers: Vec<ReceiverBindingView>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweNonceRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweNonceResponse {
+    pub address: String,
+    pub nonce: String,
+    /// EIP-4361 message to sign with `personal_sign`.
+    pub message: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SiweLoginRequest {
+    pub nonce: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub address: String,
+    /// Send as `Authorization: Bearer <token>`.
+    pub token: String,
+    pub expires_at: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthMetrics {
+    pub enabled: bool,
+    pub active_keys: usize,
+    /// Refused `/validate`, `/settle` and x402 payments since startup, by API key.
+    pub rejected_payments: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BanView {
+    /// `ip:<address>` or `key:<keyId>`.
+    pub subject: String,
+    pub remaining_secs: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitMetrics {
+    pub enabled: bool,
+    /// Clients with a bucket that is not full, per scope.
+    pub tracked_ips: usize,
+    pub tracked_api_keys: usize,
+    pub tracked_owners: usize,
+    /// Requests answered with `429` since startup, per scope.
+    pub limited_ip: u64,
+    pub limited_api_key: u64,
+    pub limited_owner: u64,
+    pub rejected_banned: u64,
+    pub signature_failures: u64,
+    pub bans_issued: u64,
+    pub active_bans: Vec<BanView>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsResponse {
+    pub leadership: LeadershipMetrics,
+    pub channel_cache: CacheMetrics,
+    /// Present only when the group-commit write pipeline is enabled.
+    pub write_pipeline: Option<WritePipelineMetrics>,
+    /// Present only on read-only replicas.
+    pub replication: Option<ReplicationMetrics>,
+    pub auth: AuthMetrics,
+    pub rate_limits: RateLimitMetrics,
+}
+
 impl ChannelView {
     pub fn from_state(channel: &ChannelState) -> Self {
         Self {
@@ -116,6 +851,28 @@ impl ChannelView {
                     balance: r.balance.to_string(),
                 })
                 .collect(),
+            status: channel.effective_status(),
+            close_tx_hash: channel.close_tx_hash.clone(),
+            closed_at: channel.closed_at,
+            archived: false,
+            redacted: false,
+        }
+    }
+
+    /// What a recipient of the channel may see: its own balance, not the other
+    /// recipients or the signatures over them.
+    pub fn redact_for(self, recipient: Address) -> Self {
+        let recipient = format!("0x{:x}", recipient);
+        Self {
+            user_signature: String::new(),
+            sequencer_signature: String::new(),
+            recipients: self
+                .recipients
+                .into_iter()
+                .filter(|r| r.recipient_address == recipient)
+                .collect(),
+            redacted: true,
+            ..self
         }
     }
 }
@@ -1,29 +1,96 @@
 use utoipa::OpenApi;
 
-use crate::{handlers, model};
+use crate::{handlers, model, x402};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         handlers::health,
+        handlers::metrics,
+        handlers::domain,
+        handlers::list_channels,
         handlers::list_channels_by_owner,
+        handlers::list_quarantined_channels,
+        handlers::owner_portfolio,
+        handlers::recipient_balances,
         handlers::seed_channel,
         handlers::get_channel,
+        handlers::channel_proof,
+        handlers::prepare_channel_update,
         handlers::finalize_channel,
+        handlers::request_finalize,
+        handlers::close_channel,
+        handlers::close_status,
         handlers::validate_pay_in_channel,
-        handlers::settle
+        handlers::settle,
+        handlers::x402_verify,
+        handlers::x402_settle,
+        handlers::x402_requirements,
+        handlers::receiver_challenge,
+        handlers::bind_receiver,
+        handlers::list_receivers,
+        handlers::unbind_receiver,
+        handlers::siwe_nonce,
+        handlers::siwe_login
     ),
     components(
         schemas(
             model::SeedChannelRequest,
             model::PayInChannelRequest,
             model::FinalizeChannelRequest,
+            model::OwnerCloseRequest,
+            model::TransactionStatus,
+            model::CloseStatusResponse,
+            model::RecipientFinalizeRequest,
+            model::RecipientFinalizeResponse,
+            model::FinalizeRequestStatus,
             model::FeeForPayment,
+            model::X402Request,
+            model::PaymentPayload,
+            model::PaymentRequirements,
+            model::X402RequirementsRequest,
+            x402::X402RequirementsResponse,
+            model::CpcRequirementsExtra,
+            model::TypedDataDomain,
+            model::X402VerifyResponse,
+            model::X402SettleResponse,
             model::ChannelView,
+            model::ChannelStatus,
+            model::ChannelSort,
+            model::SortOrder,
+            model::ChannelSummary,
+            model::ChannelListResponse,
+            model::ChannelProofResponse,
+            model::PrepareChannelUpdateRequest,
+            model::PrepareChannelUpdateResponse,
             model::ChannelsByOwnerResponse,
+            model::PortfolioChannel,
+            model::OwnerPortfolioResponse,
+            model::RecipientChannelBalance,
+            model::RecipientBalancesResponse,
             model::RecipientView,
             model::PayInChannelResponse,
-            model::FinalizeChannelResponse
+            model::FinalizeChannelResponse,
+            model::QuarantinedChannel,
+            model::QuarantinedChannelsResponse,
+            model::CacheMetrics,
+            model::WritePipelineMetrics,
+            model::LeadershipMetrics,
+            model::ReplicationMetrics,
+            model::MetricsResponse,
+            model::AuthMetrics,
+            model::BanView,
+            model::RateLimitMetrics,
+            model::DomainResponse,
+            model::ReceiverChallengeRequest,
+            model::ReceiverChallengeResponse,
+            model::BindReceiverRequest,
+            model::ReceiverBindingView,
+            model::ReceiverBindingsResponse,
+            model::SiweNonceRequest,
+            model::SiweNonceResponse,
+            model::SiweLoginRequest,
+            model::SessionResponse
         )
     ),
     tags(
@@ -36,3 +103,24 @@ use crate::{handlers, model};
     )
 )]
 pub struct ApiDoc;
+
+/// Schemas of the x402 v2 wire shapes, merged into `ApiDoc` when the feature is enabled.
+#[cfg(feature = "x402-v2")]
+#[derive(OpenApi)]
+#[openapi(components(schemas(
+    x402::v2::ResourceInfo,
+    x402::v2::PaymentRequirementsV2,
+    x402::v2::PaymentPayloadV2,
+    x402::v2::X402RequestV2,
+    x402::v2::PaymentRequired
+)))]
+struct X402V2Doc;
+
+/// The served OpenAPI document.
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    #[allow(unused_mut)]
+    let mut doc = ApiDoc::openapi();
+    #[cfg(feature = "x402-v2")]
+    doc.merge(X402V2Doc::openapi());
+    doc
+}
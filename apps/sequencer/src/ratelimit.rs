@@ -0,0 +1,405 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use ethers_core::types::Address;
+use tracing::warn;
+
+use crate::{
+    auth::Caller,
+    config::{Config, RateLimit},
+    error::AppError,
+    model::{BanView, RateLimitMetrics},
+};
+
+/// Address a request came from, added to the request extensions by `limit_clients`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub IpAddr);
+
+/// Who sent a payment: the client address and, with auth on, the API key.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub ip: Option<IpAddr>,
+    pub caller: Option<Caller>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets of one scope, created full on first use.
+struct Buckets {
+    limit: RateLimit,
+    entries: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Buckets {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token, or returns how many seconds until one is available.
+    fn take(&self, key: &str, now: Instant) -> Result<(), u64> {
+        if self.limit.per_sec <= 0.0 {
+            return Ok(());
+        }
+        let burst = self.limit.burst.max(1.0);
+        let mut entries = self.entries.lock().expect("rate limit buckets poisoned");
+        let bucket = entries.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.limit.per_sec).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(((1.0 - bucket.tokens) / self.limit.per_sec).ceil() as u64)
+    }
+
+    /// Drops buckets that have refilled completely; they are recreated full on next use.
+    fn prune(&self, now: Instant) {
+        if self.limit.per_sec <= 0.0 {
+            return;
+        }
+        let burst = self.limit.burst.max(1.0);
+        self.entries
+            .lock()
+            .expect("rate limit buckets poisoned")
+            .retain(|_, bucket| {
+                bucket.tokens + now.saturating_duration_since(bucket.updated).as_secs_f64() * self.limit.per_sec < burst
+            });
+    }
+
+    fn len(&self) -> usize {
+        self.entries.lock().expect("rate limit buckets poisoned").len()
+    }
+}
+
+#[derive(Default)]
+struct LimitCounters {
+    limited_ip: AtomicU64,
+    limited_api_key: AtomicU64,
+    limited_owner: AtomicU64,
+    rejected_banned: AtomicU64,
+    signature_failures: AtomicU64,
+    bans_issued: AtomicU64,
+}
+
+/// Throttles clients with token buckets per client address, per API key and per channel
+/// owner, and bans clients that keep sending payments with bad signatures.
+///
+/// Address limits apply to every route (see `limit_clients`). Key limits and bans of keys
+/// are checked on the payment routes before any signature is recovered, so a flood of
+/// invalid signatures costs a map lookup instead of an ECDSA recovery. Owner limits are
+/// charged only once the signature has proven the owner, so nobody can spend another
+/// owner's tokens. State is per process; each node of a deployment enforces its own limits.
+pub struct RateLimiter {
+    enabled: bool,
+    trusted_proxy_hops: usize,
+    ip: Buckets,
+    api_key: Buckets,
+    owner: Buckets,
+    failure_limit: u32,
+    failure_window: Duration,
+    ban: Duration,
+    /// Signature failures per subject, with the start of their window.
+    failures: Mutex<HashMap<String, (u32, Instant)>>,
+    /// Banned subjects (`ip:<addr>` or `key:<keyId>`) and when their ban ends.
+    bans: Mutex<HashMap<String, Instant>>,
+    counters: LimitCounters,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            enabled: config.rate_limit_enabled,
+            trusted_proxy_hops: config.rate_limit_trusted_proxy_hops,
+            ip: Buckets::new(config.rate_limit_ip),
+            api_key: Buckets::new(config.rate_limit_key),
+            owner: Buckets::new(config.rate_limit_owner),
+            failure_limit: config.signature_failure_limit,
+            failure_window: Duration::from_secs(config.signature_failure_window_secs),
+            ban: Duration::from_secs(config.ban_secs),
+            failures: Mutex::new(HashMap::new()),
+            bans: Mutex::new(HashMap::new()),
+            counters: LimitCounters::default(),
+        }
+    }
+
+    /// Admits a request from `ip`, unless the address is banned or out of tokens.
+    pub fn admit_ip(&self, ip: IpAddr) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let subject = format!("ip:{ip}");
+        self.check_ban(&subject)?;
+        self.ip.take(&subject, Instant::now()).map_err(|retry_after| {
+            self.counters.limited_ip.fetch_add(1, Ordering::Relaxed);
+            AppError::too_many_requests(format!("rate limit exceeded for {ip}"), retry_after)
+        })
+    }
+
+    /// Admits a payment from the client's API key, unless the key is banned or out of tokens.
+    pub fn admit_caller(&self, client: &Client) -> Result<(), AppError> {
+        let (true, Some(caller)) = (self.enabled, &client.caller) else {
+            return Ok(());
+        };
+        let subject = format!("key:{}", caller.key_id);
+        self.check_ban(&subject)?;
+        self.api_key.take(&subject, Instant::now()).map_err(|retry_after| {
+            self.counters.limited_api_key.fetch_add(1, Ordering::Relaxed);
+            AppError::too_many_requests(format!("rate limit exceeded for api key {}", caller.key_id), retry_after)
+        })
+    }
+
+    /// Admits a payment from a channel of `owner`, shared by all of the owner's channels.
+    pub fn admit_owner(&self, owner: Address) -> Result<(), AppError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.owner.take(&format!("owner:0x{:x}", owner), Instant::now()).map_err(|retry_after| {
+            self.counters.limited_owner.fetch_add(1, Ordering::Relaxed);
+            AppError::too_many_requests(format!("rate limit exceeded for channel owner 0x{:x}", owner), retry_after)
+        })
+    }
+
+    /// Counts a payment whose signature did not verify against the client's address and
+    /// key; either one reaching the limit within the window is banned.
+    pub fn record_signature_failure(&self, client: &Client) {
+        if !self.enabled || self.failure_limit == 0 {
+            return;
+        }
+        self.counters.signature_failures.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+        let subjects = client
+            .ip
+            .map(|ip| format!("ip:{ip}"))
+            .into_iter()
+            .chain(client.caller.as_ref().map(|caller| format!("key:{}", caller.key_id)));
+        for subject in subjects {
+            let reached = {
+                let mut failures = self.failures.lock().expect("signature failures poisoned");
+                let entry = failures.entry(subject.clone()).or_insert((0, now));
+                if now.saturating_duration_since(entry.1) > self.failure_window {
+                    *entry = (0, now);
+                }
+                entry.0 += 1;
+                let reached = entry.0 >= self.failure_limit;
+                if reached {
+                    failures.remove(&subject);
+                }
+                reached
+            };
+            if reached {
+                warn!(subject = %subject, ban_secs = self.ban.as_secs(), "banned after repeated signature failures");
+                self.counters.bans_issued.fetch_add(1, Ordering::Relaxed);
+                self.bans.lock().expect("bans poisoned").insert(subject, now + self.ban);
+            }
+        }
+    }
+
+    fn check_ban(&self, subject: &str) -> Result<(), AppError> {
+        let now = Instant::now();
+        let mut bans = self.bans.lock().expect("bans poisoned");
+        match bans.get(subject) {
+            Some(until) if *until > now => {
+                self.counters.rejected_banned.fetch_add(1, Ordering::Relaxed);
+                let remaining = until.saturating_duration_since(now).as_secs_f64().ceil() as u64;
+                Err(AppError::too_many_requests(
+                    format!("{subject} is banned after repeated signature failures"),
+                    remaining,
+                ))
+            }
+            Some(_) => {
+                bans.remove(subject);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The client address: with `trusted_proxy_hops` set, the `X-Forwarded-For` entry that
+    /// many hops from the right (`forwarded[len - trusted_proxy_hops]`), which is the address
+    /// the outermost trusted proxy received from; otherwise, or when the header has fewer
+    /// entries or an unparsable one there, the peer of the connection.
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        // Every proxy appends the address it received from, so only entries counted from
+        // the right were written by trusted hops; anything further left is client-supplied.
+        if self.trusted_proxy_hops > 0 {
+            let forwarded = request
+                .headers()
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .collect::<Vec<_>>();
+            let client = forwarded
+                .len()
+                .checked_sub(self.trusted_proxy_hops)
+                .and_then(|index| forwarded[index].trim().parse().ok());
+            if client.is_some() {
+                return client;
+            }
+        }
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+
+    /// Forgets refilled buckets, expired bans and stale failure counts every `interval`.
+    pub async fn run_cleanup(self: Arc<Self>, interval: Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let now = Instant::now();
+            self.ip.prune(now);
+            self.api_key.prune(now);
+            self.owner.prune(now);
+            self.bans.lock().expect("bans poisoned").retain(|_, until| *until > now);
+            self.failures
+                .lock()
+                .expect("signature failures poisoned")
+                .retain(|_, (_, since)| now.saturating_duration_since(*since) <= self.failure_window);
+        }
+    }
+
+    pub fn metrics(&self) -> RateLimitMetrics {
+        let now = Instant::now();
+        let mut active_bans: Vec<BanView> = self
+            .bans
+            .lock()
+            .expect("bans poisoned")
+            .iter()
+            .filter(|(_, until)| **until > now)
+            .map(|(subject, until)| BanView {
+                subject: subject.clone(),
+                remaining_secs: until.saturating_duration_since(now).as_secs(),
+            })
+            .collect();
+        active_bans.sort_by(|a, b| a.subject.cmp(&b.subject));
+        RateLimitMetrics {
+            enabled: self.enabled,
+            tracked_ips: self.ip.len(),
+            tracked_api_keys: self.api_key.len(),
+            tracked_owners: self.owner.len(),
+            limited_ip: self.counters.limited_ip.load(Ordering::Relaxed),
+            limited_api_key: self.counters.limited_api_key.load(Ordering::Relaxed),
+            limited_owner: self.counters.limited_owner.load(Ordering::Relaxed),
+            rejected_banned: self.counters.rejected_banned.load(Ordering::Relaxed),
+            signature_failures: self.counters.signature_failures.load(Ordering::Relaxed),
+            bans_issued: self.counters.bans_issued.load(Ordering::Relaxed),
+            active_bans,
+        }
+    }
+}
+
+/// Applies the per-address limit and bans to every request and records the client address.
+pub async fn limit_clients(
+    State(limiter): State<Arc<RateLimiter>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Some(ip) = limiter.client_ip(&request) {
+        limiter.admit_ip(ip)?;
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    fn buckets(per_sec: f64, burst: f64) -> Buckets {
+        Buckets::new(RateLimit { per_sec, burst })
+    }
+
+    #[test]
+    fn burst_then_retry_after() {
+        let limit = buckets(0.5, 3.0);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limit.take("client", now), Ok(()));
+        }
+        // One token refills every two seconds.
+        assert_eq!(limit.take("client", now), Err(2));
+        assert_eq!(limit.take("client", now + Duration::from_secs(1)), Err(1));
+    }
+
+    #[test]
+    fn tokens_refill_up_to_burst() {
+        let limit = buckets(1.0, 2.0);
+        let now = Instant::now();
+        assert_eq!(limit.take("client", now), Ok(()));
+        assert_eq!(limit.take("client", now), Ok(()));
+        assert!(limit.take("client", now).is_err());
+
+        let later = now + Duration::from_secs(60);
+        assert_eq!(limit.take("client", later), Ok(()));
+        assert_eq!(limit.take("client", later), Ok(()));
+        assert_eq!(limit.take("client", later), Err(1));
+    }
+
+    #[test]
+    fn keys_have_separate_buckets() {
+        let limit = buckets(1.0, 1.0);
+        let now = Instant::now();
+        assert_eq!(limit.take("a", now), Ok(()));
+        assert!(limit.take("a", now).is_err());
+        assert_eq!(limit.take("b", now), Ok(()));
+    }
+
+    fn limiter(trusted_proxy_hops: usize) -> RateLimiter {
+        std::env::set_var("CHANNEL_MANAGER_ADDRESS", "0x5FbDB2315678afecb367f032d93F642f64180aa3");
+        std::env::set_var("SEQUENCER_PRIVATE_KEY", format!("{:064x}", 1));
+        let mut config = Config::from_env().unwrap();
+        config.rate_limit_trusted_proxy_hops = trusted_proxy_hops;
+        RateLimiter::new(&config)
+    }
+
+    fn forwarded(header: &str) -> Request {
+        let mut request = Request::builder().header("x-forwarded-for", header).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 443))));
+        request
+    }
+
+    #[test]
+    fn client_ip_counts_trusted_hops_from_the_right() {
+        let request = forwarded("203.0.113.9, 198.51.100.7, 192.0.2.1");
+        assert_eq!(limiter(1).client_ip(&request), Some("192.0.2.1".parse().unwrap()));
+        assert_eq!(limiter(2).client_ip(&request), Some("198.51.100.7".parse().unwrap()));
+        // More hops than entries, or no trusted hops: the header is ignored.
+        assert_eq!(limiter(4).client_ip(&request), Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(limiter(0).client_ip(&request), Some("10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn zero_rate_disables_the_limit() {
+        let limit = buckets(0.0, 1.0);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(limit.take("client", now), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+use sqlx::{postgres::PgListener, PgPool};
+use tracing::{info, warn};
+
+use crate::{cache::ChannelCache, db::CHANNEL_UPDATES, model::ReplicationMetrics};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChannelUpdate {
+    channel_id: String,
+    sequence_number: u64,
+    committed_at_ms: i64,
+}
+
+/// Keeps a read-only replica's cache in sync with the primary through `LISTEN/NOTIFY`.
+///
+/// Every committed channel write publishes a notification (see `db::write_channel`); the
+/// replica reloads that channel if it is cached. Notifications sent while the listener is
+/// disconnected are lost, so every (re)connect is followed by a full cache refresh.
+pub struct Replicator {
+    database_url: String,
+    db: PgPool,
+    channels: Arc<ChannelCache>,
+    status: Arc<ReplicationStatus>,
+}
+
+#[derive(Default)]
+pub struct ReplicationStatus {
+    connected: AtomicBool,
+    applied: AtomicU64,
+    resyncs: AtomicU64,
+    last_sequence_number: AtomicU64,
+    last_committed_at_ms: AtomicI64,
+    last_lag_ms: AtomicI64,
+}
+
+impl ReplicationStatus {
+    pub fn metrics(&self) -> ReplicationMetrics {
+        let last_committed_at_ms = self.last_committed_at_ms.load(Ordering::Relaxed);
+        ReplicationMetrics {
+            connected: self.connected.load(Ordering::Relaxed),
+            applied: self.applied.load(Ordering::Relaxed),
+            resyncs: self.resyncs.load(Ordering::Relaxed),
+            last_sequence_number: self.last_sequence_number.load(Ordering::Relaxed),
+            last_committed_at_ms,
+            last_lag_ms: self.last_lag_ms.load(Ordering::Relaxed),
+            since_last_update_ms: if last_committed_at_ms == 0 {
+                0
+            } else {
+                now_ms() - last_committed_at_ms
+            },
+        }
+    }
+}
+
+impl Replicator {
+    pub fn new(database_url: String, db: PgPool, channels: Arc<ChannelCache>) -> Self {
+        Self {
+            database_url,
+            db,
+            channels,
+            status: Arc::new(ReplicationStatus::default()),
+        }
+    }
+
+    pub fn status(&self) -> Arc<ReplicationStatus> {
+        self.status.clone()
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(err) = self.follow().await {
+                warn!(error = %err, "replication listener failed");
+            }
+            self.status.connected.store(false, Ordering::Relaxed);
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn follow(&self) -> Result<(), sqlx::Error> {
+        let mut listener = PgListener::connect(&self.database_url).await?;
+        listener.listen(CHANNEL_UPDATES).await?;
+
+        // Anything committed before LISTEN took effect is picked up by the refresh.
+        let refreshed = self.channels.refresh(&self.db).await?;
+        self.status.resyncs.fetch_add(1, Ordering::Relaxed);
+        self.status.connected.store(true, Ordering::Relaxed);
+        info!(refreshed, "replica cache resynchronized");
+
+        while let Some(notification) = listener.try_recv().await? {
+            match serde_json::from_str::<ChannelUpdate>(notification.payload()) {
+                Ok(update) => self.apply(update).await?,
+                Err(err) => warn!(error = %err, payload = notification.payload(), "invalid channel update"),
+            }
+        }
+
+        // `try_recv` returned `None`: the connection dropped and notifications may have
+        // been missed, so start over with a fresh listener and a full resync.
+        warn!("replication listener disconnected");
+        Ok(())
+    }
+
+    async fn apply(&self, update: ChannelUpdate) -> Result<(), sqlx::Error> {
+        self.channels.reload(&self.db, &update.channel_id).await?;
+        self.status.applied.fetch_add(1, Ordering::Relaxed);
+        self.status
+            .last_sequence_number
+            .store(update.sequence_number, Ordering::Relaxed);
+        self.status
+            .last_committed_at_ms
+            .store(update.committed_at_ms, Ordering::Relaxed);
+        self.status
+            .last_lag_ms
+            .store((now_ms() - update.committed_at_ms).max(0), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
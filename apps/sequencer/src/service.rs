@@ -1,72 +1,538 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use ethers_core::{types::{Address, Bytes, H256, U256}, utils::hex};
+use ethers_core::{
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, Signature, H256, U256},
+    utils::hex,
+};
 use ethers_middleware::SignerMiddleware;
-use ethers_providers::{Http, Provider};
+use ethers_providers::{Http, Middleware, Provider};
 use ethers_signers::LocalWallet;
-use tracing::info;
-use tokio::sync::RwLock;
+use ethers_signers::Signer;
+use tracing::{error, info, warn};
 
 use crate::{
+    auth::{now_secs, Authenticator, Caller, Reader},
+    cache::{ChannelCache, ChannelHandle},
     config::Config,
-    crypto::{parse_address, parse_h256, parse_u256, recover_signature, sign_update, validate_timestamp},
-    db::{save_channel},
+    crypto::{
+        channel_update_digest, channel_update_typed_data, domain_separator, encode_publish_intermediate_state, parse_address, parse_h256, parse_u256,
+        receiver_binding_typed_data, recover_close_request, recover_finalize_request, recover_receiver_binding, recover_signature, sign_update, typed_data_domain,
+        validate_timestamp, TIMESTAMP_MAX_FUTURE_SECS,
+    },
+    db::{
+        channel_statuses, delete_receiver_binding, insert_receiver_binding, insert_receiver_challenge, insert_siwe_nonce,
+        load_receiver_bindings, take_receiver_challenge, take_siwe_nonce, load_archived_channel, load_archived_channels, load_channels_by_owner, load_quarantined,
+        close_request_accepted, confirm_finalize_requests, delete_close_request, insert_close_request, insert_finalize_request, last_finalize_request, load_recipient_balances, retry_finalize_requests,
+        quarantine_reason, reseed_channel, save_channel, search_channels, ChannelFilter,
+    },
     error::AppError,
+    ha::Leadership,
+    integrity::{check_recipients, ChannelVerifier},
+    lifecycle::{transition, ChannelEvent},
     model::{
+        BindReceiverRequest,
+        ChannelListResponse,
+        ChannelProofResponse,
+        CloseStatusResponse,
+        ChannelSearchQuery,
+        ChannelSort,
         ChannelState,
+        ChannelStatus,
+        ChannelSummary,
         ChannelView,
         ChannelsByOwnerResponse,
+        CpcRequirementsExtra,
+        DomainResponse,
+        FeeForPayment,
         FinalizeChannelRequest,
         FinalizeChannelResponse,
+        FinalizeRequestStatus,
+        MetricsResponse,
+        OwnerCloseRequest,
+        OwnerPortfolioResponse,
         PayInChannelRequest,
         PayInChannelResponse,
+        PaymentRequirements,
+        PortfolioChannel,
+        PrepareChannelUpdateRequest,
+        PrepareChannelUpdateResponse,
+        QuarantinedChannel,
+        QuarantinedChannelsResponse,
         RecipientBalance,
+        RecipientBalancesResponse,
+        RecipientFinalizeRequest,
+        RecipientFinalizeResponse,
+        ReceiverBindingView,
+        ReceiverBindingsResponse,
+        ReceiverChallengeRequest,
+        ReceiverChallengeResponse,
         SeedChannelRequest,
+        SessionResponse,
+        SiweLoginRequest,
+        SiweNonceRequest,
+        SiweNonceResponse,
+        SortOrder,
+        TransactionStatus,
+        TypedDataDomain,
+        X402RequirementsRequest,
+        X402SettleResponse,
+        X402VerifyResponse,
     },
+    ratelimit::{Client, RateLimiter},
+    replica::ReplicationStatus,
+    session::{siwe_message, SIWE_NONCE_TTL_SECS},
+    writer::WritePipeline,
+    x402::{self, PaymentRequest, Rejection, X402RequirementsResponse, X402Version},
 };
 use sqlx::PgPool;
 
+const DEFAULT_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SIZE: u32 = 500;
+/// How far the timestamp of a signed owner or recipient request may be from the server clock.
+const SIGNED_REQUEST_MAX_SKEW_SECS: u64 = 300;
+/// How long a receiver has to sign a binding challenge.
+const RECEIVER_CHALLENGE_TTL_SECS: u64 = 300;
+
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
-    pub channels: Arc<RwLock<HashMap<String, ChannelState>>>,
+    pub channels: Arc<ChannelCache>,
     pub config: Arc<Config>,
     pub provider: Arc<Provider<Http>>,
     pub sequencer_wallet: LocalWallet,
+    /// ERC-20 token the channel manager holds deposits in (its `token()`).
+    pub payment_asset: Address,
+    /// EIP-712 domain checked against the chain at startup (see `resolve_domain`).
+    pub domain: Arc<DomainResponse>,
+    /// Group-commit pipeline for channel writes; `None` persists each write on its own.
+    pub write_pipeline: Option<Arc<WritePipeline>>,
+    pub leadership: Arc<Leadership>,
+    /// Set on read-only replicas that follow the primary through `LISTEN/NOTIFY`.
+    pub replication: Option<Arc<ReplicationStatus>>,
+    pub auth: Arc<Authenticator>,
+    pub limits: Arc<RateLimiter>,
 }
 
 pub async fn seed_channel(state: &AppState, payload: SeedChannelRequest) -> Result<ChannelView, AppError> {
+    let fencing_token = state.leadership.ensure_leader()?;
     let channel_id = parse_h256(&payload.channel_id)?;
     let owner = parse_address(&payload.owner)?;
     let balance = parse_u256(&payload.balance)?;
 
+    let (user_signature, signature_timestamp) = match (payload.user_signature, payload.signature_timestamp) {
+        (Some(signature), Some(timestamp)) => {
+            verify_opening_signature(state, channel_id, owner, payload.expiry_timestamp, &signature, timestamp).await?;
+            (signature, timestamp)
+        }
+        (None, None) => (String::new(), 0),
+        _ => {
+            return Err(AppError::bad_request(
+                "userSignature and signatureTimestamp must be given together",
+            ))
+        }
+    };
+
     let channel_state = ChannelState {
         channel_id,
         owner,
         balance,
         expiry_ts: payload.expiry_timestamp,
         sequence_number: 0,
-        user_signature: String::new(),
+        user_signature,
         sequencer_signature: String::new(),
-        signature_timestamp: 0,
+        signature_timestamp,
         recipients: Vec::new(),
+        status: ChannelStatus::Open,
+        close_tx_hash: None,
+        closed_at: None,
+    };
+
+    let key = format!("0x{:x}", channel_id);
+    match state.channels.get_or_load(&state.db, &key).await? {
+        Some(handle) => {
+            let mut channel = handle.lock().await;
+            transition(
+                channel.effective_status(),
+                ChannelEvent::Seed {
+                    signed: channel.sequence_number > 0,
+                },
+            )?;
+            persist_channel(state, &channel_state, fencing_token).await?;
+            *channel = channel_state;
+            Ok(ChannelView::from_state(&channel))
+        }
+        None => {
+            if load_archived_channel(&state.db, &key).await?.is_some() {
+                return Err(AppError::conflict("channel is archived and cannot be reseeded"));
+            }
+            if quarantine_reason(&state.db, &key).await?.is_some() {
+                transition(ChannelStatus::Quarantined, ChannelEvent::Seed { signed: false })?;
+                reseed_channel(&state.db, &channel_state, fencing_token).await?;
+            } else {
+                persist_channel(state, &channel_state, fencing_token).await?;
+            }
+            let handle = channel_handle(state, &key).await?;
+            let channel = handle.lock().await;
+            Ok(ChannelView::from_state(&channel))
+        }
+    }
+}
+
+/// Checks the `openChannel` signature: the owner's sequence-0 `ChannelData` with no
+/// recipients, timestamped no later than the channel expiry.
+async fn verify_opening_signature(
+    state: &AppState,
+    channel_id: H256,
+    owner: Address,
+    expiry_ts: u64,
+    signature: &str,
+    timestamp: u64,
+) -> Result<(), AppError> {
+    if timestamp > expiry_ts {
+        return Err(AppError::bad_request("timestamp is after channel expiry"));
+    }
+    let config = state.config.clone();
+    let signature = signature.to_string();
+    let recovered = run_blocking(move || {
+        recover_signature(
+            channel_id,
+            0,
+            timestamp,
+            &[],
+            config.chain_id,
+            config.channel_manager,
+            &signature,
+        )
+    })
+    .await?;
+    if recovered != owner {
+        return Err(AppError::bad_request("opening signature is not from the channel owner"));
+    }
+    Ok(())
+}
+
+pub async fn get_channel(
+    state: &AppState,
+    reader: Reader,
+    channel_id: String,
+    include_archived: bool,
+) -> Result<ChannelView, AppError> {
+    let key = channel_key(&channel_id)?;
+    if let Some(handle) = state.channels.get_or_load(&state.db, &key).await? {
+        let channel = handle.lock().await;
+        return visible_view(reader, &channel, ChannelView::from_state(&channel));
+    }
+    if let Some(reason) = quarantine_reason(&state.db, &key).await? {
+        return Err(AppError::conflict(format!("channel is quarantined: {reason}")));
+    }
+    if include_archived {
+        if let Some(channel) = load_archived_channel(&state.db, &key).await? {
+            let view = ChannelView {
+                archived: true,
+                ..ChannelView::from_state(&channel)
+            };
+            return visible_view(reader, &channel, view);
+        }
+    }
+    Err(AppError::not_found("channel not found"))
+}
+
+/// Owners and operators see a channel in full, recipients redacted, anyone else nothing.
+fn visible_view(reader: Reader, channel: &ChannelState, view: ChannelView) -> Result<ChannelView, AppError> {
+    match reader {
+        Reader::Operator => Ok(view),
+        Reader::Wallet(wallet) if wallet == channel.owner => Ok(view),
+        Reader::Wallet(wallet) if is_recipient(channel, wallet) => Ok(view.redact_for(wallet)),
+        Reader::Wallet(_) => Err(not_a_participant()),
+    }
+}
+
+/// Checks that a wallet reader is the channel's owner or one of its recipients.
+fn ensure_participant(reader: Reader, channel: &ChannelState) -> Result<(), AppError> {
+    match reader {
+        Reader::Wallet(wallet) if wallet != channel.owner && !is_recipient(channel, wallet) => Err(not_a_participant()),
+        _ => Ok(()),
+    }
+}
+
+fn ensure_owner(reader: Reader, channel: &ChannelState) -> Result<(), AppError> {
+    match reader {
+        Reader::Wallet(wallet) if wallet != channel.owner => {
+            Err(AppError::forbidden("only the channel owner can prepare its updates"))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_recipient(channel: &ChannelState, address: Address) -> bool {
+    channel.recipients.iter().any(|r| r.recipient_address == address)
+}
+
+fn not_a_participant() -> AppError {
+    AppError::forbidden("session wallet is neither the owner nor a recipient of this channel")
+}
+
+/// Lists channels held in the local store, one page at a time.
+pub async fn list_channels(
+    state: &AppState,
+    reader: Reader,
+    query: ChannelSearchQuery,
+) -> Result<ChannelListResponse, AppError> {
+    let mut filter = ChannelFilter {
+        owner: query.owner.as_deref().map(address_key).transpose()?,
+        recipient: query.recipient.as_deref().map(address_key).transpose()?,
+        status: query.status,
+        expires_after: query.expires_after,
+        expires_before: query.expires_before,
+        min_remaining: query
+            .min_remaining
+            .as_deref()
+            .map(|value| parse_u256(value).map(|value| value.to_string()))
+            .transpose()?,
+        updated_since: query.updated_since,
+    };
+    scope_to_reader(reader, &mut filter)?;
+    let sort = query.sort.unwrap_or_default();
+    let order = query.order.unwrap_or_default();
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE) as usize;
+    let after = query
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(cursor, sort, order))
+        .transpose()?;
+
+    let mut channels = search_channels(
+        &state.db,
+        &filter,
+        sort,
+        order == SortOrder::Desc,
+        after.as_ref().map(|(key, channel_id)| (key.as_str(), channel_id.as_str())),
+        limit + 1,
+    )
+    .await?;
+
+    let next_cursor = if channels.len() > limit {
+        channels.truncate(limit);
+        channels.last().map(|last| encode_cursor(last, sort, order))
+    } else {
+        None
+    };
+    Ok(ChannelListResponse { channels, next_cursor })
+}
+
+/// Limits a wallet's listing to channels it owns or is paid by, its own channels when the
+/// query names neither.
+fn scope_to_reader(reader: Reader, filter: &mut ChannelFilter) -> Result<(), AppError> {
+    let Reader::Wallet(wallet) = reader else {
+        return Ok(());
+    };
+    let wallet = format!("0x{:x}", wallet);
+    match (&filter.owner, &filter.recipient) {
+        (None, None) => filter.owner = Some(wallet),
+        (owner, recipient) if owner.as_ref() == Some(&wallet) || recipient.as_ref() == Some(&wallet) => {}
+        _ => {
+            return Err(AppError::forbidden("session wallet can only list channels it owns or is a recipient of"))
+        }
+    }
+    Ok(())
+}
+
+/// Cursors are the hex-encoded `sort:order:key:channelId` of the last row of a page.
+fn encode_cursor(last: &ChannelSummary, sort: ChannelSort, order: SortOrder) -> String {
+    let key = match sort {
+        ChannelSort::ChannelId => last.channel_id.clone(),
+        ChannelSort::Expiry => last.expiry_timestamp.to_string(),
+        ChannelSort::UpdatedAt => last.updated_at.to_string(),
+        ChannelSort::Remaining => last.remaining_capacity.clone(),
+    };
+    hex::encode(format!("{}:{}:{key}:{}", sort.as_str(), order_str(order), last.channel_id))
+}
+
+fn decode_cursor(cursor: &str, sort: ChannelSort, order: SortOrder) -> Result<(String, String), AppError> {
+    let invalid = || AppError::bad_request("invalid cursor");
+    let decoded = hex::decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or_else(invalid)?;
+    let mut parts = decoded.splitn(4, ':');
+    let (Some(cursor_sort), Some(cursor_order), Some(key), Some(channel_id)) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(invalid());
+    };
+    if cursor_sort != sort.as_str() || cursor_order != order_str(order) {
+        return Err(AppError::bad_request("cursor was issued for a different sort order"));
+    }
+    let key_is_valid = match sort {
+        ChannelSort::ChannelId => key == channel_id,
+        ChannelSort::Expiry | ChannelSort::UpdatedAt => key.parse::<u64>().is_ok(),
+        ChannelSort::Remaining => parse_u256(key).is_ok(),
     };
+    if !key_is_valid {
+        return Err(invalid());
+    }
+    let channel_id = channel_key(channel_id).map_err(|_| invalid())?;
+    Ok((key.to_string(), channel_id))
+}
+
+fn order_str(order: SortOrder) -> &'static str {
+    match order {
+        SortOrder::Asc => "asc",
+        SortOrder::Desc => "desc",
+    }
+}
+
+/// Builds a `publishIntermediateChannelState` bundle from the latest co-signed state,
+/// after checking both signatures against it.
+/// Recipients get the full proof too: publishing a state on-chain needs every recipient
+/// and both signatures.
+pub async fn channel_proof(state: &AppState, reader: Reader, channel_id: String) -> Result<ChannelProofResponse, AppError> {
+    let handle = channel_handle(state, &channel_key(&channel_id)?).await?;
+    let channel = handle.lock().await.clone();
+    if let Reader::Wallet(wallet) = reader {
+        if wallet != channel.owner && !is_recipient(&channel, wallet) {
+            return Err(not_a_participant());
+        }
+    }
+    if channel.sequence_number == 0 {
+        return Err(AppError::conflict("channel has no co-signed state yet"));
+    }
+
+    let verifier = ChannelVerifier::new(&state.config, state.sequencer_wallet.address());
+    let config = state.config.clone();
+    run_blocking(move || {
+        if let Err(reason) = verifier.verify(&channel) {
+            error!(channel_id = %format!("0x{:x}", channel.channel_id), reason = %reason, "stored state failed verification");
+            return Err(AppError::Internal);
+        }
+
+        let digest = channel_update_digest(
+            channel.channel_id,
+            channel.sequence_number,
+            channel.signature_timestamp,
+            &channel.recipients,
+            config.chain_id,
+            config.channel_manager,
+        );
+        let calldata = encode_publish_intermediate_state(
+            channel.channel_id,
+            channel.sequence_number,
+            channel.signature_timestamp,
+            &channel.recipients,
+            parse_signature_bytes(&channel.user_signature)?.to_vec(),
+            parse_signature_bytes(&channel.sequencer_signature)?.to_vec(),
+        );
+
+        Ok(ChannelProofResponse {
+            channel_id: format!("0x{:x}", channel.channel_id),
+            chain_id: config.chain_id,
+            channel_manager: format!("0x{:x}", config.channel_manager),
+            status: channel.effective_status(),
+            expiry_timestamp: channel.expiry_ts,
+            sequence_number: channel.sequence_number,
+            timestamp: channel.signature_timestamp,
+            recipients: channel
+                .recipients
+                .iter()
+                .map(|r| format!("0x{:x}", r.recipient_address))
+                .collect(),
+            amounts: channel.recipients.iter().map(|r| r.balance.to_string()).collect(),
+            user_signature: channel.user_signature.clone(),
+            sequencer_signature: channel.sequencer_signature.clone(),
+            digest: format!("0x{:x}", digest),
+            calldata: format!("0x{}", hex::encode(calldata)),
+        })
+    })
+    .await
+}
+
+/// Builds the next `ChannelData` for a payment exactly as a settle will check it, so
+/// clients only sign. Nothing is stored; the result is stale once another settle lands.
+pub async fn prepare_channel_update(
+    state: &AppState,
+    reader: Reader,
+    channel_id: String,
+    payload: PrepareChannelUpdateRequest,
+) -> Result<PrepareChannelUpdateResponse, AppError> {
+    let handle = channel_handle(state, &channel_key(&channel_id)?).await?;
+    let channel = handle.lock().await.clone();
+    // The update lists every recipient's balance and only the owner can sign it.
+    ensure_owner(reader, &channel)?;
+    let recipients = pay_recipients(
+        &channel,
+        &payload.receiver,
+        &payload.amount,
+        payload.fee_for_payment.as_ref(),
+    )?;
+    transition(channel.effective_status(), ChannelEvent::Settle)?;
+    check_recipients(&recipients, channel.balance, state.config.max_recipients)?;
+
+    let config = &state.config;
+    let sequence_number = channel.sequence_number + 1;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+        .min(channel.expiry_ts);
+    let digest = channel_update_digest(
+        channel.channel_id,
+        sequence_number,
+        timestamp,
+        &recipients,
+        config.chain_id,
+        config.channel_manager,
+    );
+    let typed_data = channel_update_typed_data(
+        channel.channel_id,
+        sequence_number,
+        timestamp,
+        &recipients,
+        config.chain_id,
+        config.channel_manager,
+    );
 
-    save_channel(&state.db, &channel_state).await?;
+    Ok(PrepareChannelUpdateResponse {
+        channel_id: format!("0x{:x}", channel.channel_id),
+        sequence_number,
+        timestamp,
+        recipients: recipients.iter().map(|r| format!("0x{:x}", r.recipient_address)).collect(),
+        amounts: recipients.iter().map(|r| r.balance.to_string()).collect(),
+        digest: format!("0x{:x}", digest),
+        typed_data,
+    })
+}
 
-    let mut channels = state.channels.write().await;
-    channels.insert(payload.channel_id.clone(), channel_state);
+pub async fn list_quarantined_channels(state: &AppState) -> Result<QuarantinedChannelsResponse, AppError> {
+    let channels = load_quarantined(&state.db)
+        .await?
+        .into_iter()
+        .map(|(channel_id, reason, quarantined_at)| QuarantinedChannel {
+            channel_id,
+            reason,
+            quarantined_at: quarantined_at as u64,
+        })
+        .collect();
+    Ok(QuarantinedChannelsResponse { channels })
+}
 
-    let view = ChannelView::from_state(channels.get(&payload.channel_id).unwrap());
-    Ok(view)
+pub fn domain(state: &AppState) -> DomainResponse {
+    state.domain.as_ref().clone()
 }
 
-pub async fn get_channel(state: &AppState, channel_id: String) -> Result<ChannelView, AppError> {
-    let channels = state.channels.read().await;
-    let channel = channels
-        .get(&channel_id)
-        .ok_or_else(|| AppError::not_found("channel not found"))?;
-    Ok(ChannelView::from_state(channel))
+pub fn metrics(state: &AppState) -> MetricsResponse {
+    MetricsResponse {
+        leadership: state.leadership.metrics(),
+        channel_cache: state.channels.metrics(),
+        write_pipeline: state.write_pipeline.as_ref().map(|pipeline| pipeline.metrics()),
+        replication: state.replication.as_ref().map(|status| status.metrics()),
+        auth: state.auth.metrics(),
+        rate_limits: state.limits.metrics(),
+    }
 }
 
 pub async fn validate_pay_in_channel(
@@ -74,15 +540,13 @@ pub async fn validate_pay_in_channel(
     payload: PayInChannelRequest,
 ) -> Result<PayInChannelResponse, AppError> {
     let channel_id = parse_h256(&payload.channel_id)?;
-    let channels = state.channels.read().await;
-    let channel = channels
-        .get(&payload.channel_id)
-        .ok_or_else(|| AppError::not_found("channel not found"))?;
+    let handle = channel_handle(state, &format!("0x{:x}", channel_id)).await?;
+    let channel = handle.lock().await.clone();
 
     if payload.sequence_number == channel.sequence_number {
         if payload.user_signature == channel.user_signature && payload.timestamp == channel.signature_timestamp {
             return Ok(PayInChannelResponse {
-                channel: ChannelView::from_state(channel),
+                channel: ChannelView::from_state(&channel),
             });
         }
         return Err(AppError::bad_request("sequence already processed"));
@@ -100,21 +564,338 @@ pub async fn validate_pay_in_channel(
         );
     }
 
-    let updated = compute_next_state(channel, &payload, state)?;
+    let config = state.config.clone();
+    let limits = state.limits.clone();
+    let updated = run_blocking(move || admit_next_state(&channel, &payload, &config, &limits)).await?;
 
     Ok(PayInChannelResponse {
         channel: ChannelView::from_state(&updated),
     })
 }
 
+/// `/validate` on behalf of a client. The caller must be bound to the receiver and within
+/// its rate limits.
+pub async fn validate_as(
+    state: &AppState,
+    client: &Client,
+    payload: PayInChannelRequest,
+) -> Result<PayInChannelResponse, AppError> {
+    let result = match admit_payment(state, client, &payload) {
+        Ok(()) => validate_pay_in_channel(state, payload).await,
+        Err(err) => Err(err),
+    };
+    record_refusal(state, client, "validate", result)
+}
+
+/// `/settle` on behalf of a client. The caller must be bound to the receiver and within
+/// its rate limits.
+pub async fn settle_as(
+    state: &AppState,
+    client: &Client,
+    payload: PayInChannelRequest,
+) -> Result<PayInChannelResponse, AppError> {
+    let result = match admit_payment(state, client, &payload) {
+        Ok(()) => settle(state, payload).await,
+        Err(err) => Err(err),
+    };
+    record_refusal(state, client, "settle", result)
+}
+
+/// Cheap checks that run before any signature is recovered: the caller's bans and rate
+/// limit, and its bindings. The channel owner's rate limit is only charged once the
+/// signature has proven the owner (see [`admit_next_state`]).
+fn admit_payment(state: &AppState, client: &Client, payload: &PayInChannelRequest) -> Result<(), AppError> {
+    state.limits.admit_caller(client)?;
+    check_payees(state, client.caller.as_ref(), payload)
+}
+
+/// A payment may only credit addresses bound to the caller: its receiver, and its fee
+/// destination unless that is the sequencer's own `FEE_DESTINATION_ADDRESS`.
+fn check_payees(state: &AppState, caller: Option<&Caller>, payload: &PayInChannelRequest) -> Result<(), AppError> {
+    state.auth.check_payee(caller, parse_address(&payload.receiver)?)?;
+    if let Some(fee) = &payload.fee_for_payment {
+        let fee_address = parse_address(&fee.fee_destination_address)?;
+        if Some(fee_address) != state.config.fee_destination_address {
+            state.auth.check_payee(caller, fee_address)?;
+        }
+    }
+    Ok(())
+}
+
+/// Counts refused payments against the caller, and bad signatures towards a ban.
+/// Sequencer faults and rate limiting are not counted.
+fn record_refusal<T>(state: &AppState, client: &Client, action: &str, result: Result<T, AppError>) -> Result<T, AppError> {
+    if let Err(err) = &result {
+        if is_signature_failure(err) {
+            state.limits.record_signature_failure(client);
+        }
+        if !matches!(err, AppError::Internal | AppError::Unavailable(_) | AppError::TooManyRequests { .. }) {
+            state.auth.record_rejection(client.caller.as_ref(), action, &err.to_string());
+        }
+    }
+    result
+}
+
+/// Whether a refused channel update failed on its user signature, which is what a
+/// client pays an ECDSA recovery for.
+fn is_signature_failure(err: &AppError) -> bool {
+    matches!(err, AppError::InvalidSignature(_))
+}
+
+/// x402 facilitator `verify` for the `cpc` scheme: checks the payload against the
+/// requirements, then validates the channel update like `/validate`.
+pub async fn x402_verify(
+    state: &AppState,
+    client: &Client,
+    body: serde_json::Value,
+) -> Result<X402VerifyResponse, AppError> {
+    let version = x402::requested_version(&body);
+    let rejection = match x402_accept(state, client, body).await {
+        Ok(request) => match validate_pay_in_channel(state, request.payload).await {
+            Ok(response) => {
+                return Ok(X402VerifyResponse {
+                    is_valid: true,
+                    invalid_reason: None,
+                    invalid_message: None,
+                    payer: Some(response.channel.owner),
+                })
+            }
+            Err(err) => x402_refusal(state, client, err)?,
+        },
+        Err(rejection) => rejection?,
+    };
+    state.auth.record_rejection(client.caller.as_ref(), "x402 verify", &rejection.message);
+    Ok(X402VerifyResponse {
+        is_valid: false,
+        invalid_reason: Some(rejection.reason(version)),
+        invalid_message: rejection.detail(version),
+        payer: None,
+    })
+}
+
+/// x402 facilitator `settle` for the `cpc` scheme: checks the payload against the
+/// requirements, then co-signs and persists the channel update like `/settle`.
+pub async fn x402_settle(
+    state: &AppState,
+    client: &Client,
+    body: serde_json::Value,
+) -> Result<X402SettleResponse, AppError> {
+    let version = x402::requested_version(&body);
+    // v1 answers in the network name the server asked for, v2 always in CAIP-2.
+    let mut network = x402::network(state.config.chain_id);
+    let outcome = match x402_accept(state, client, body).await {
+        Ok(request) => {
+            if version == X402Version::V1 {
+                network = request.requirements.network;
+            }
+            match settle(state, request.payload).await {
+                Ok(settled) => Ok(settled),
+                Err(err) => Err(x402_refusal(state, client, err)?),
+            }
+        }
+        Err(rejection) => Err(rejection?),
+    };
+    if let Err(rejection) = &outcome {
+        state.auth.record_rejection(client.caller.as_ref(), "x402 settle", &rejection.message);
+    }
+    let mut response = match outcome {
+        Ok(settled) => X402SettleResponse {
+            success: true,
+            error_reason: None,
+            error_message: None,
+            payer: Some(settled.channel.owner),
+            transaction: settled.channel.sequencer_signature,
+            network,
+            channel_id: Some(settled.channel.channel_id),
+            sequence_number: Some(settled.channel.sequence_number),
+            payment_response: String::new(),
+            payment_response_header: String::new(),
+        },
+        Err(rejection) => X402SettleResponse {
+            success: false,
+            error_reason: Some(rejection.reason(version)),
+            error_message: rejection.detail(version),
+            payer: None,
+            transaction: String::new(),
+            network,
+            channel_id: None,
+            sequence_number: None,
+            payment_response: String::new(),
+            payment_response_header: String::new(),
+        },
+    };
+    response.payment_response = x402::payment_response_header(&response);
+    response.payment_response_header = version.payment_response_header().to_string();
+    Ok(response)
+}
+
+/// Decodes a facilitator request and checks it against this deployment. Payments the
+/// caller may not direct to their payees are not rejections but `403`s, like on `/settle`.
+async fn x402_accept(
+    state: &AppState,
+    client: &Client,
+    body: serde_json::Value,
+) -> Result<PaymentRequest, Result<Rejection, AppError>> {
+    let request = x402::decode(body).map_err(Ok)?;
+    x402::check(&request, state.config.chain_id, state.payment_asset).map_err(Ok)?;
+    if let Err(err) = admit_payment(state, client, &request.payload) {
+        return Err(x402_refusal(state, client, err));
+    }
+    Ok(request)
+}
+
+/// Maps a refused x402 payment to a rejection like `Rejection::from_error`, counting bad
+/// signatures towards a ban and logging refusals that stay HTTP errors.
+fn x402_refusal(state: &AppState, client: &Client, err: AppError) -> Result<Rejection, AppError> {
+    if is_signature_failure(&err) {
+        state.limits.record_signature_failure(client);
+    }
+    let refusal = Rejection::from_error(err);
+    if let Err(err) = &refusal {
+        if !matches!(err, AppError::Internal | AppError::Unavailable(_) | AppError::TooManyRequests { .. }) {
+            state.auth.record_rejection(client.caller.as_ref(), "x402", &err.to_string());
+        }
+    }
+    refusal
+}
+
+/// Builds an x402 `accepts` entry for the `cpc` scheme from the current channel state.
+///
+/// With `channelId` that channel must accept settles and have `price` left. With `owner`
+/// the owner's open channel with the most remaining capacity (then the latest expiry) that
+/// covers `price` and outlives `maxTimeoutSeconds` is picked.
+pub async fn x402_requirements(
+    state: &AppState,
+    request: X402RequirementsRequest,
+) -> Result<X402RequirementsResponse, AppError> {
+    let version = match request.x402_version {
+        Some(number) => X402Version::from_number(number)
+            .ok_or_else(|| AppError::bad_request(format!("unsupported x402Version {number}")))?,
+        None => X402Version::V1,
+    };
+    let price = parse_u256(&request.price)?;
+    let pay_to = parse_address(&request.pay_to)?;
+    let max_timeout_seconds = request.max_timeout_seconds.unwrap_or(x402::DEFAULT_MAX_TIMEOUT_SECS);
+    let channel = match (request.channel_id.as_deref(), request.owner.as_deref()) {
+        (Some(channel_id), _) => Some(payable_channel(state, &channel_key(channel_id)?, price).await?),
+        (None, Some(owner)) => Some(pick_owner_channel(state, owner, price, max_timeout_seconds).await?),
+        (None, None) => None,
+    };
+
+    let config = &state.config;
+    let extra = CpcRequirementsExtra {
+        channel_id: channel.as_ref().map(|channel| format!("0x{:x}", channel.channel_id)),
+        next_sequence_number: channel.as_ref().map(|channel| channel.sequence_number + 1),
+        channel_expiry: channel.as_ref().map(|channel| channel.expiry_ts),
+        channel_manager: format!("0x{:x}", config.channel_manager),
+        domain: typed_data_domain(config.chain_id, config.channel_manager),
+        timestamp_skew_seconds: TIMESTAMP_MAX_FUTURE_SECS,
+        max_recipients: config.max_recipients,
+        fee_destination_address: config.fee_destination_address.map(|address| format!("0x{:x}", address)),
+    };
+    let requirements = PaymentRequirements {
+        scheme: x402::SCHEME.to_string(),
+        network: x402::network(config.chain_id),
+        max_amount_required: price.to_string(),
+        resource: request.resource,
+        description: request.description.unwrap_or_else(|| x402::DEFAULT_DESCRIPTION.to_string()),
+        mime_type: request.mime_type.unwrap_or_else(|| x402::DEFAULT_MIME_TYPE.to_string()),
+        output_schema: request.output_schema,
+        pay_to: format!("0x{:x}", pay_to),
+        max_timeout_seconds,
+        asset: format!("0x{:x}", state.payment_asset),
+        extra: Some(serde_json::to_value(extra).map_err(|_| AppError::Internal)?),
+    };
+    Ok(X402RequirementsResponse::new(requirements, version))
+}
+
+/// Current state of a channel that can take a settle of `price`.
+async fn payable_channel(state: &AppState, channel_id: &str, price: U256) -> Result<ChannelState, AppError> {
+    let handle = channel_handle(state, channel_id).await?;
+    let channel = handle.lock().await.clone();
+    transition(channel.effective_status(), ChannelEvent::Settle)?;
+    if channel.balance.saturating_sub(spent_amount(&channel)) < price {
+        return Err(AppError::conflict("channel has not enough remaining capacity for the price"));
+    }
+    Ok(channel)
+}
+
+async fn pick_owner_channel(
+    state: &AppState,
+    owner: &str,
+    price: U256,
+    max_timeout_seconds: u64,
+) -> Result<ChannelState, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let filter = ChannelFilter {
+        owner: Some(address_key(owner)?),
+        status: Some(ChannelStatus::Open),
+        expires_after: Some(now + max_timeout_seconds),
+        min_remaining: Some(price.to_string()),
+        ..ChannelFilter::default()
+    };
+    let candidates = search_channels(
+        &state.db,
+        &filter,
+        ChannelSort::Remaining,
+        true,
+        None,
+        DEFAULT_PAGE_SIZE as usize,
+    )
+    .await?;
+    let best = candidates
+        .iter()
+        .filter_map(|summary| Some((parse_u256(&summary.remaining_capacity).ok()?, summary)))
+        .max_by_key(|(remaining, summary)| (*remaining, summary.expiry_timestamp))
+        .map(|(_, summary)| summary.channel_id.clone())
+        .ok_or_else(|| AppError::not_found("owner has no open channel that can cover the price"))?;
+    payable_channel(state, &best, price).await
+}
+
 pub async fn finalize_channel(
     state: &AppState,
     payload: FinalizeChannelRequest,
 ) -> Result<FinalizeChannelResponse, AppError> {
-    let channels = state.channels.read().await;
-    let channel = channels
-        .get(&payload.channel_id)
-        .ok_or_else(|| AppError::not_found("channel not found"))?;
+    let fencing_token = state.leadership.ensure_leader()?;
+    let (handle, channel) = begin_finalize(state, &channel_key(&payload.channel_id)?, None, fencing_token).await?;
+    let submitted = match final_close_transaction(state, &channel) {
+        Ok(transaction) => send_final_close(state, transaction, std::slice::from_ref(&handle), fencing_token).await,
+        Err(err) => Err(err),
+    };
+    complete_finalize(state, &handle, &submitted, fencing_token).await?;
+
+    Ok(FinalizeChannelResponse {
+        transaction_hash: submitted?,
+    })
+}
+
+/// Checks that a channel can be closed with its latest user-signed state and persists it
+/// as `finalizing`, returning the state to submit. With `expected_sequence`, the latest
+/// state must be exactly that sequence.
+///
+/// Persisted before the transaction is sent, so settles stay rejected even if this node
+/// dies mid-finalize; `recover_finalize` settles such channels later. The channel lock is
+/// released for the RPC round trip.
+pub(crate) async fn begin_finalize(
+    state: &AppState,
+    channel_id: &str,
+    expected_sequence: Option<u64>,
+    fencing_token: Option<u64>,
+) -> Result<(ChannelHandle, ChannelState), AppError> {
+    let handle = channel_handle(state, channel_id).await?;
+    let mut guard = handle.lock().await;
+    let finalizing = transition(guard.effective_status(), ChannelEvent::BeginFinalize)?;
+    let channel = guard.clone();
+
+    if let Some(expected) = expected_sequence.filter(|expected| *expected != channel.sequence_number) {
+        return Err(AppError::conflict(format!(
+            "channel is at sequence {}, not {expected}",
+            channel.sequence_number
+        )));
+    }
 
     if channel.user_signature.is_empty() {
         return Err(AppError::bad_request("channel has no user signature"));
@@ -125,20 +906,212 @@ pub async fn finalize_channel(
 
     validate_timestamp(channel.signature_timestamp, channel.expiry_ts)?;
 
-    let recovered = recover_signature(
-        channel.channel_id,
-        channel.sequence_number,
-        channel.signature_timestamp,
-        &channel.recipients,
-        state.config.chain_id,
-        state.config.channel_manager,
-        &channel.user_signature,
-    )?;
+    let config = state.config.clone();
+    let signed = channel.clone();
+    let recovered = run_blocking(move || {
+        recover_signature(
+            signed.channel_id,
+            signed.sequence_number,
+            signed.signature_timestamp,
+            &signed.recipients,
+            config.chain_id,
+            config.channel_manager,
+            &signed.user_signature,
+        )
+    })
+    .await?;
 
     if recovered != channel.owner {
-        return Err(AppError::bad_request("invalid user signature"));
+        return Err(AppError::invalid_signature("invalid user signature"));
+    }
+
+    let mut pending = channel;
+    pending.status = finalizing;
+    persist_channel(state, &pending, fencing_token).await?;
+    *guard = pending.clone();
+    drop(guard);
+    Ok((handle, pending))
+}
+
+/// Moves a `finalizing` channel back to `open` if nothing was signed for it. A channel
+/// whose transaction was signed stays `finalizing`, with the hash `send_final_close`
+/// stored, until `recover_finalize` sees the receipt.
+pub(crate) async fn complete_finalize(
+    state: &AppState,
+    handle: &ChannelHandle,
+    submitted: &Result<String, AppError>,
+    fencing_token: Option<u64>,
+) -> Result<(), AppError> {
+    let mut guard = handle.lock().await;
+    if submitted.is_ok() || guard.close_tx_hash.is_some() {
+        return Ok(());
+    }
+    let mut updated = guard.clone();
+    updated.status = transition(guard.status, ChannelEvent::FinalizeFailed)?;
+    persist_channel(state, &updated, fencing_token).await?;
+    *guard = updated;
+    Ok(())
+}
+
+/// What the finalize sweep does with a `finalizing` channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FinalizeOutcome {
+    /// The close transaction may still land; check again on the next sweep.
+    Wait,
+    /// The channel is closed on-chain.
+    Close,
+    /// The close failed and the channel is still open on-chain.
+    Reopen,
+}
+
+/// Decides a `finalizing` channel's fate from its close transaction. Until the channel is
+/// `stale`, a transaction the node does not know may simply not be broadcast yet.
+/// `open_on_chain` only matters once the transaction failed: the channel may still have
+/// been closed some other way.
+fn finalize_outcome(transaction: TransactionStatus, stale: bool, open_on_chain: bool) -> FinalizeOutcome {
+    match transaction {
+        TransactionStatus::Confirmed => FinalizeOutcome::Close,
+        TransactionStatus::Pending => FinalizeOutcome::Wait,
+        TransactionStatus::None if !stale => FinalizeOutcome::Wait,
+        TransactionStatus::None | TransactionStatus::Reverted if open_on_chain => FinalizeOutcome::Reopen,
+        TransactionStatus::None | TransactionStatus::Reverted => FinalizeOutcome::Close,
+    }
+}
+
+/// Settles a `finalizing` channel from its stored close transaction and the chain:
+/// `closed` once the transaction is mined or the channel is gone on-chain, back to `open`
+/// if the transaction reverted or never reached the chain. Finalize requests follow the
+/// channel. A transaction still waiting in the mempool is left for the next sweep.
+pub(crate) async fn recover_finalize(
+    state: &AppState,
+    channel_id: &str,
+    stale: bool,
+    fencing_token: Option<u64>,
+) -> Result<Option<ChannelStatus>, AppError> {
+    let handle = channel_handle(state, channel_id).await?;
+    let mut guard = handle.lock().await;
+    if guard.status != ChannelStatus::Finalizing {
+        return Ok(None);
+    }
+
+    let transaction = match &guard.close_tx_hash {
+        Some(transaction_hash) => close_transaction_status(state, transaction_hash).await?,
+        None => TransactionStatus::None,
+    };
+    let failed = match transaction {
+        TransactionStatus::Reverted => true,
+        TransactionStatus::None => stale,
+        TransactionStatus::Pending | TransactionStatus::Confirmed => false,
+    };
+    let open_on_chain = !failed || channel_open_on_chain(state, guard.channel_id).await?;
+    let mut updated = guard.clone();
+    match finalize_outcome(transaction, stale, open_on_chain) {
+        FinalizeOutcome::Wait => return Ok(None),
+        FinalizeOutcome::Close => {
+            updated.status = transition(guard.status, ChannelEvent::FinalizeSubmitted)?;
+            updated.closed_at = Some(now_secs());
+            persist_channel(state, &updated, fencing_token).await?;
+            confirm_finalize_requests(&state.db, channel_id, updated.close_tx_hash.as_deref(), now_secs()).await?;
+        }
+        // An owner's accepted close is never reopened: sign and send it again instead.
+        FinalizeOutcome::Reopen if close_request_accepted(&state.db, channel_id, guard.sequence_number).await? => {
+            let channel = guard.clone();
+            drop(guard);
+            let transaction = final_close_transaction(state, &channel)?;
+            let transaction_hash =
+                send_final_close(state, transaction, std::slice::from_ref(&handle), fencing_token).await?;
+            info!(channel_id = %channel_id, transaction_hash = %transaction_hash, "owner close resubmitted");
+            return Ok(Some(ChannelStatus::Finalizing));
+        }
+        FinalizeOutcome::Reopen => {
+            let error = match transaction {
+                TransactionStatus::Reverted => "close transaction reverted",
+                _ => "close transaction never reached the chain",
+            };
+            updated.status = transition(guard.status, ChannelEvent::FinalizeFailed)?;
+            updated.close_tx_hash = None;
+            persist_channel(state, &updated, fencing_token).await?;
+            retry_finalize_requests(&state.db, channel_id, error, now_secs()).await?;
+        }
+    }
+    *guard = updated;
+    Ok(Some(guard.status))
+}
+
+/// Where a close transaction is: mined (`confirmed` or `reverted`), waiting in the
+/// mempool (`pending`), or unknown to the node (`none`), because it was dropped or not
+/// broadcast yet.
+async fn close_transaction_status(state: &AppState, transaction_hash: &str) -> Result<TransactionStatus, AppError> {
+    let hash = parse_h256(transaction_hash)?;
+    let receipt = state
+        .provider
+        .get_transaction_receipt(hash)
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    if let Some(receipt) = receipt {
+        return Ok(if receipt.status.is_some_and(|status| status.as_u64() == 1) {
+            TransactionStatus::Confirmed
+        } else {
+            TransactionStatus::Reverted
+        });
+    }
+    let pending = state
+        .provider
+        .get_transaction(hash)
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    Ok(if pending.is_some() { TransactionStatus::Pending } else { TransactionStatus::None })
+}
+
+/// Whether the channel manager still holds the channel; closing deletes it.
+async fn channel_open_on_chain(state: &AppState, channel_id: H256) -> Result<bool, AppError> {
+    let contract = channel_manager_contract(state.provider.clone(), state.config.channel_manager);
+    let (_, _, expiry_time, _): (Address, U256, U256, U256) = contract
+        .method("channels", channel_id)
+        .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?
+        .call()
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    Ok(!expiry_time.is_zero())
+}
+
+/// Signs a close transaction, stores its hash on every `finalizing` channel it closes and
+/// only then broadcasts it, so a crash at any point leaves enough for `recover_finalize`.
+pub(crate) async fn send_final_close(
+    state: &AppState,
+    mut transaction: TypedTransaction,
+    handles: &[ChannelHandle],
+    fencing_token: Option<u64>,
+) -> Result<String, AppError> {
+    let client = SignerMiddleware::new(state.provider.as_ref().clone(), state.sequencer_wallet.clone());
+    client
+        .fill_transaction(&mut transaction, None)
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    let signature = state
+        .sequencer_wallet
+        .sign_transaction_sync(&transaction)
+        .map_err(|e| AppError::bad_request(format!("signing error: {e}")))?;
+    let transaction_hash = format!("0x{:x}", transaction.hash(&signature));
+
+    for handle in handles {
+        let mut guard = handle.lock().await;
+        let mut updated = guard.clone();
+        updated.close_tx_hash = Some(transaction_hash.clone());
+        persist_channel(state, &updated, fencing_token).await?;
+        *guard = updated;
     }
 
+    state
+        .provider
+        .send_raw_transaction(transaction.rlp_signed(&signature))
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    Ok(transaction_hash)
+}
+
+/// Builds the `finalCloseBySequencer` transaction for the given state.
+fn final_close_transaction(state: &AppState, channel: &ChannelState) -> Result<TypedTransaction, AppError> {
     let recipients: Vec<Address> = channel.recipients.iter().map(|r| r.recipient_address).collect();
     let amounts: Vec<U256> = channel.recipients.iter().map(|r| r.balance).collect();
     let signature_bytes = parse_signature_bytes(&channel.user_signature)?;
@@ -162,27 +1135,240 @@ pub async fn finalize_channel(
             ),
         )
         .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?;
-    let pending = call
-        .send()
-        .await
-        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    Ok(call.tx)
+}
 
-    Ok(FinalizeChannelResponse {
-        transaction_hash: format!("0x{:x}", pending.tx_hash()),
+/// Closes a channel early on its owner's signed request: the channel stops accepting
+/// settles and its latest co-signed state is finalized on-chain right away.
+pub async fn close_channel(state: &AppState, payload: OwnerCloseRequest) -> Result<CloseStatusResponse, AppError> {
+    let fencing_token = state.leadership.ensure_leader()?;
+    let channel_id = channel_key(&payload.channel_id)?;
+    check_request_timestamp(payload.timestamp)?;
+
+    let owner = channel_handle(state, &channel_id).await?.lock().await.owner;
+    let config = state.config.clone();
+    let (signed_channel, signature) = (parse_h256(&channel_id)?, payload.signature);
+    let signature_hex = signature.clone();
+    let signer = run_blocking(move || {
+        recover_close_request(
+            signed_channel,
+            payload.sequence_number,
+            payload.timestamp,
+            config.chain_id,
+            config.channel_manager,
+            &signature,
+        )
+    })
+    .await?;
+    if signer != owner {
+        return Err(AppError::bad_request("close request is not signed by the channel owner"));
+    }
+
+    // The signed request carries no nonce, so each (channel, sequence) is accepted once.
+    let accepted = insert_close_request(
+        &state.db,
+        &channel_id,
+        payload.sequence_number,
+        &signature_hex,
+        payload.timestamp,
+        now_secs(),
+    )
+    .await?;
+    if !accepted {
+        return Err(AppError::conflict("close request was already accepted"));
+    }
+    let (handle, channel) =
+        match begin_finalize(state, &channel_id, Some(payload.sequence_number), fencing_token).await {
+            Ok(started) => started,
+            Err(err) => {
+                delete_close_request(&state.db, &channel_id, payload.sequence_number).await?;
+                return Err(err);
+            }
+        };
+
+    // Once accepted the channel stays `finalizing` and takes no settles; a failed send is
+    // retried by the finalize sweep rather than reopening the channel.
+    let submitted = match final_close_transaction(state, &channel) {
+        Ok(transaction) => send_final_close(state, transaction, std::slice::from_ref(&handle), fencing_token).await,
+        Err(err) => Err(err),
+    };
+    let transaction_hash = match submitted {
+        Ok(transaction_hash) => {
+            info!(channel_id = %channel_id, transaction_hash = %transaction_hash, "owner close sent");
+            Some(transaction_hash)
+        }
+        Err(err) => {
+            warn!(channel_id = %channel_id, error = %err, "owner close not sent; will retry");
+            None
+        }
+    };
+
+    let status = handle.lock().await.effective_status();
+    Ok(CloseStatusResponse {
+        channel_id,
+        status,
+        transaction_status: if transaction_hash.is_some() { TransactionStatus::Pending } else { TransactionStatus::None },
+        transaction_hash,
+        block_number: None,
+    })
+}
+
+/// Reports a channel's close transaction and whether it has been mined, looking in the
+/// archive for channels that were already moved there.
+pub async fn close_status(
+    state: &AppState,
+    reader: Reader,
+    channel_id: String,
+) -> Result<CloseStatusResponse, AppError> {
+    let key = channel_key(&channel_id)?;
+    let channel = match state.channels.get_or_load(&state.db, &key).await? {
+        Some(handle) => handle.lock().await.clone(),
+        None => match load_archived_channel(&state.db, &key).await? {
+            Some(channel) => channel,
+            None => channel_handle(state, &key).await?.lock().await.clone(),
+        },
+    };
+    ensure_participant(reader, &channel)?;
+
+    let (transaction_status, block_number) = match &channel.close_tx_hash {
+        None => (TransactionStatus::None, None),
+        Some(transaction_hash) => {
+            let receipt = state
+                .provider
+                .get_transaction_receipt(parse_h256(transaction_hash)?)
+                .await
+                .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+            match receipt {
+                None => (TransactionStatus::Pending, None),
+                Some(receipt) => {
+                    let status = if receipt.status.is_some_and(|status| status.as_u64() == 1) {
+                        TransactionStatus::Confirmed
+                    } else {
+                        TransactionStatus::Reverted
+                    };
+                    (status, receipt.block_number.map(|block| block.as_u64()))
+                }
+            }
+        }
+    };
+
+    Ok(CloseStatusResponse {
+        channel_id: key,
+        status: channel.effective_status(),
+        transaction_hash: channel.close_tx_hash,
+        transaction_status,
+        block_number,
+    })
+}
+
+/// Builds one `finalCloseBySequencerBatch` transaction closing several channels. The
+/// contract reverts the whole batch if any closure is invalid.
+pub(crate) fn final_close_batch_transaction(state: &AppState, channels: &[ChannelState]) -> Result<TypedTransaction, AppError> {
+    let mut batch = Vec::with_capacity(channels.len());
+    for channel in channels {
+        batch.push((
+            channel.channel_id,
+            U256::from(channel.sequence_number),
+            U256::from(channel.signature_timestamp),
+            channel.recipients.iter().map(|r| r.recipient_address).collect::<Vec<Address>>(),
+            channel.recipients.iter().map(|r| r.balance).collect::<Vec<U256>>(),
+            parse_signature_bytes(&channel.user_signature)?,
+        ));
+    }
+
+    let client = Arc::new(SignerMiddleware::new(
+        state.provider.as_ref().clone(),
+        state.sequencer_wallet.clone(),
+    ));
+    let contract = payment_channel_finalize_contract(client, state.config.channel_manager);
+
+    let call = contract
+        .method::<_, H256>("finalCloseBySequencerBatch", (batch,))
+        .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?;
+    Ok(call.tx)
+}
+
+/// Queues a recipient's signed request to close a channel that owes it. The channel is
+/// closed by the next finalize batch (see `finalize::FinalizeBatcher`).
+pub async fn request_finalize(
+    state: &AppState,
+    payload: RecipientFinalizeRequest,
+) -> Result<RecipientFinalizeResponse, AppError> {
+    state.leadership.ensure_leader()?;
+    let channel_id = channel_key(&payload.channel_id)?;
+    let recipient = parse_address(&payload.recipient)?;
+    let reason = payload.reason.unwrap_or_default();
+
+    let now = check_request_timestamp(payload.timestamp)?;
+
+    let config = state.config.clone();
+    let (signed_channel, signed_reason, signature) = (parse_h256(&channel_id)?, reason.clone(), payload.signature);
+    let signer = run_blocking(move || {
+        recover_finalize_request(
+            signed_channel,
+            recipient,
+            payload.timestamp,
+            &signed_reason,
+            config.chain_id,
+            config.channel_manager,
+            &signature,
+        )
+    })
+    .await?;
+    if signer != recipient {
+        return Err(AppError::bad_request("invalid recipient signature"));
+    }
+
+    let handle = channel_handle(state, &channel_id).await?;
+    let channel = handle.lock().await.clone();
+    transition(channel.effective_status(), ChannelEvent::BeginFinalize)?;
+    let owed = channel
+        .recipients
+        .iter()
+        .find(|r| r.recipient_address == recipient)
+        .map(|r| r.balance)
+        .unwrap_or_default();
+    if owed.is_zero() {
+        return Err(AppError::bad_request("recipient has no balance in this channel"));
+    }
+    if owed < state.config.finalize_request_min_owed {
+        return Err(AppError::bad_request(format!(
+            "owed amount {owed} is below the finalize request minimum of {}",
+            state.config.finalize_request_min_owed
+        )));
+    }
+
+    let recipient_key = format!("0x{:x}", recipient);
+    let cooldown = state.config.finalize_request_cooldown_secs;
+    let Some(request_id) = insert_finalize_request(&state.db, &channel_id, &recipient_key, &reason, now, cooldown).await?
+    else {
+        let last = last_finalize_request(&state.db, &channel_id, &recipient_key).await?.unwrap_or(now);
+        let elapsed = now.saturating_sub(last);
+        return Err(AppError::conflict(format!(
+            "finalize already requested {elapsed}s ago; retry in {}s",
+            cooldown.saturating_sub(elapsed)
+        )));
+    };
+    info!(request_id, channel_id = %channel_id, recipient = %recipient_key, "finalize requested by recipient");
+    Ok(RecipientFinalizeResponse {
+        request_id,
+        channel_id,
+        owed: owed.to_string(),
+        status: FinalizeRequestStatus::Pending,
     })
 }
 
 pub async fn settle(state: &AppState, payload: PayInChannelRequest) -> Result<PayInChannelResponse, AppError> {
+    let fencing_token = state.leadership.ensure_leader()?;
     let channel_id = parse_h256(&payload.channel_id)?;
-    let mut channels = state.channels.write().await;
-    let channel = channels
-        .get_mut(&payload.channel_id)
-        .ok_or_else(|| AppError::not_found("channel not found"))?;
+    let handle = channel_handle(state, &format!("0x{:x}", channel_id)).await?;
+    // Only settles on this channel wait here; other channels proceed in parallel.
+    let mut channel = handle.lock().await;
 
     if payload.sequence_number == channel.sequence_number {
         if payload.user_signature == channel.user_signature && payload.timestamp == channel.signature_timestamp {
             return Ok(PayInChannelResponse {
-                channel: ChannelView::from_state(channel),
+                channel: ChannelView::from_state(&channel),
             });
         }
         return Err(AppError::bad_request("sequence already processed"));
@@ -200,80 +1386,106 @@ pub async fn settle(state: &AppState, payload: PayInChannelRequest) -> Result<Pa
         );
     }
 
-    let updated = compute_next_state(channel, &payload, state)?;
-    let sequencer_signature = sign_update(
-        &state.sequencer_wallet,
-        updated.channel_id,
-        updated.sequence_number,
-        updated.signature_timestamp,
-        &updated.recipients,
-        state.config.chain_id,
-        state.config.channel_manager,
-    )?;
+    let current = channel.clone();
+    let config = state.config.clone();
+    let wallet = state.sequencer_wallet.clone();
+    let limits = state.limits.clone();
+    let updated = run_blocking(move || {
+        let updated = admit_next_state(&current, &payload, &config, &limits)?;
+        co_sign(updated, &config, &wallet)
+    })
+    .await?;
 
-    let mut updated = updated;
-    updated.sequencer_signature = sequencer_signature;
+    // Persist before touching the cache so an evicted entry never loses an update.
+    persist_channel(state, &updated, fencing_token).await?;
     *channel = updated;
 
-    save_channel(&state.db, channel).await?;
-
     Ok(PayInChannelResponse {
-        channel: ChannelView::from_state(channel),
+        channel: ChannelView::from_state(&channel),
     })
 }
 
-fn compute_next_state(
+/// Validates `payload` against `channel` and co-signs the resulting state.
+///
+/// This is CPU-bound (signature recovery and EIP-712 signing); async callers should
+/// run it through [`run_blocking`] so it stays off the executor threads.
+pub fn sign_next_state(
     channel: &ChannelState,
     payload: &PayInChannelRequest,
-    state: &AppState,
+    config: &Config,
+    wallet: &LocalWallet,
 ) -> Result<ChannelState, AppError> {
-    let receiver = parse_address(&payload.receiver)?;
-    let amount = parse_u256(&payload.amount)?;
-    let fee = payload
-        .fee_for_payment
-        .as_ref()
-        .map(|fee| -> Result<(Address, U256), AppError> {
-            let fee_address = parse_address(&fee.fee_destination_address)?;
-            let fee_amount = parse_u256(&fee.fee_amount_curds)?;
-            Ok((fee_address, fee_amount))
-        })
-        .transpose()?;
+    co_sign(compute_next_state(channel, payload, config)?, config, wallet)
+}
 
-    if amount.is_zero() {
-        return Err(AppError::bad_request("amount must be greater than zero"));
-    }
+/// Validates `payload` against `channel`, then charges the owner's rate limit: a payment
+/// only spends the owner's tokens once its signature has proven it comes from the owner.
+fn admit_next_state(
+    channel: &ChannelState,
+    payload: &PayInChannelRequest,
+    config: &Config,
+    limits: &RateLimiter,
+) -> Result<ChannelState, AppError> {
+    let updated = compute_next_state(channel, payload, config)?;
+    limits.admit_owner(channel.owner)?;
+    Ok(updated)
+}
 
-    validate_timestamp(payload.timestamp, channel.expiry_ts)?;
+/// Adds the sequencer's signature to a validated next state.
+fn co_sign(mut updated: ChannelState, config: &Config, wallet: &LocalWallet) -> Result<ChannelState, AppError> {
+    updated.sequencer_signature = sign_update(
+        wallet,
+        updated.channel_id,
+        updated.sequence_number,
+        updated.signature_timestamp,
+        &updated.recipients,
+        config.chain_id,
+        config.channel_manager,
+    )?;
+    Ok(updated)
+}
 
-    let mut recipients = channel.recipients.clone();
-    add_amount(&mut recipients, receiver, amount);
-    if let Some((fee_address, fee_amount)) = fee {
-        add_amount(&mut recipients, fee_address, fee_amount);
-    }
+/// Runs CPU-heavy work on tokio's blocking pool.
+pub async fn run_blocking<T, F>(work: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await.map_err(|e| {
+        error!(error = %e, "blocking task failed");
+        AppError::Internal
+    })?
+}
 
-    if recipients.len() > state.config.max_recipients {
-        return Err(AppError::bad_request("max recipients exceeded"));
-    }
+fn compute_next_state(
+    channel: &ChannelState,
+    payload: &PayInChannelRequest,
+    config: &Config,
+) -> Result<ChannelState, AppError> {
+    let recipients = pay_recipients(
+        channel,
+        &payload.receiver,
+        &payload.amount,
+        payload.fee_for_payment.as_ref(),
+    )?;
+    let status = transition(channel.effective_status(), ChannelEvent::Settle)?;
 
-    let total = recipients
-        .iter()
-        .fold(U256::zero(), |acc, r| acc + r.balance);
-    if total > channel.balance {
-        return Err(AppError::bad_request("exceeds channel capacity"));
-    }
+    validate_timestamp(payload.timestamp, channel.expiry_ts)?;
+
+    check_recipients(&recipients, channel.balance, config.max_recipients)?;
 
     let recovered = recover_signature(
         channel.channel_id,
         payload.sequence_number,
         payload.timestamp,
         &recipients,
-        state.config.chain_id,
-        state.config.channel_manager,
+        config.chain_id,
+        config.channel_manager,
         &payload.user_signature,
     )?;
 
     if recovered != channel.owner {
-        return Err(AppError::bad_request("invalid user signature"));
+        return Err(AppError::invalid_signature("invalid user signature"));
     }
 
     let mut updated = channel.clone();
@@ -282,12 +1494,160 @@ fn compute_next_state(
     updated.sequencer_signature = String::new();
     updated.signature_timestamp = payload.timestamp;
     updated.recipients = recipients;
+    updated.status = status;
 
     Ok(updated)
 }
 
-pub async fn list_channels_by_owner(state: &AppState, owner: String) -> Result<ChannelsByOwnerResponse, AppError> {
+/// Channel ids the owner has opened on-chain; with `status`, only those stored with that
+/// (effective) status.
+pub async fn list_channels_by_owner(
+    state: &AppState,
+    reader: Reader,
+    owner: String,
+    status: Option<ChannelStatus>,
+) -> Result<ChannelsByOwnerResponse, AppError> {
+    let owner_address = parse_address(&owner)?;
+    reader.ensure(owner_address)?;
+    let mut channel_ids = owner_channel_ids(state, owner_address).await?;
+
+    if let Some(status) = status {
+        let statuses = channel_statuses(&state.db, &channel_ids).await?;
+        channel_ids.retain(|channel_id| statuses.get(channel_id) == Some(&status));
+    }
+
+    Ok(ChannelsByOwnerResponse {
+        owner: format!("0x{:x}", owner_address),
+        channel_ids,
+    })
+}
+
+/// Joins the owner's on-chain channel list with what the sequencer holds. Channels the
+/// sequencer does not know (never seeded) or the contract does not list for this owner
+/// are kept and flagged through `on_chain` / `in_sequencer`.
+pub async fn owner_portfolio(
+    state: &AppState,
+    reader: Reader,
+    owner: String,
+) -> Result<OwnerPortfolioResponse, AppError> {
     let owner_address = parse_address(&owner)?;
+    reader.ensure(owner_address)?;
+    let owner_key = format!("0x{:x}", owner_address);
+    let on_chain_ids = owner_channel_ids(state, owner_address).await?;
+    let on_chain: HashSet<&str> = on_chain_ids.iter().map(String::as_str).collect();
+
+    let stored = load_channels_by_owner(&state.db, &owner_key).await?;
+    let mut live: HashMap<String, ChannelState> = stored
+        .iter()
+        .map(|channel| (format!("0x{:x}", channel.channel_id), channel.clone()))
+        .collect();
+    let missing: Vec<String> = on_chain_ids
+        .iter()
+        .filter(|channel_id| !live.contains_key(*channel_id))
+        .cloned()
+        .collect();
+    let mut archived: HashMap<String, ChannelState> = load_archived_channels(&state.db, &missing)
+        .await?
+        .into_iter()
+        .map(|channel| (format!("0x{:x}", channel.channel_id), channel))
+        .collect();
+    // Catches quarantined rows, which are stored but never loaded.
+    let statuses = channel_statuses(&state.db, &missing).await?;
+
+    let mut total_capacity = U256::zero();
+    let mut total_spent = U256::zero();
+    let mut total_remaining = U256::zero();
+    let mut channels = Vec::with_capacity(on_chain_ids.len());
+    for channel_id in &on_chain_ids {
+        if let Some(channel) = live.remove(channel_id) {
+            channels.push(portfolio_channel(&channel, true, false));
+        } else if let Some(channel) = archived.remove(channel_id) {
+            channels.push(portfolio_channel(&channel, true, true));
+        } else {
+            channels.push(PortfolioChannel {
+                channel_id: channel_id.clone(),
+                on_chain: true,
+                in_sequencer: statuses.contains_key(channel_id),
+                archived: false,
+                status: statuses.get(channel_id).copied(),
+                capacity: None,
+                spent: None,
+                remaining: None,
+                expiry_timestamp: None,
+                next_sequence_number: None,
+            });
+        }
+    }
+    for channel in &stored {
+        if !on_chain.contains(format!("0x{:x}", channel.channel_id).as_str()) {
+            channels.push(portfolio_channel(channel, false, false));
+        }
+    }
+
+    for channel in &stored {
+        let spent = spent_amount(channel);
+        total_capacity = total_capacity.saturating_add(channel.balance);
+        total_spent = total_spent.saturating_add(spent);
+        if channel.effective_status() == ChannelStatus::Open {
+            total_remaining = total_remaining.saturating_add(channel.balance.saturating_sub(spent));
+        }
+    }
+
+    Ok(OwnerPortfolioResponse {
+        owner: owner_key,
+        channels,
+        total_capacity: total_capacity.to_string(),
+        total_spent: total_spent.to_string(),
+        total_remaining: total_remaining.to_string(),
+    })
+}
+
+/// What every channel that is not closed yet owes `recipient`, per its latest co-signed state.
+pub async fn recipient_balances(
+    state: &AppState,
+    reader: Reader,
+    recipient: String,
+) -> Result<RecipientBalancesResponse, AppError> {
+    reader.ensure(parse_address(&recipient)?)?;
+    let recipient = address_key(&recipient)?;
+    let channels = load_recipient_balances(&state.db, &recipient).await?;
+    let total_balance = channels
+        .iter()
+        .map(|channel| parse_u256(&channel.balance))
+        .try_fold(U256::zero(), |total, balance| balance.map(|balance| total.saturating_add(balance)))?;
+    Ok(RecipientBalancesResponse {
+        recipient,
+        channels,
+        total_balance: total_balance.to_string(),
+    })
+}
+
+fn portfolio_channel(channel: &ChannelState, on_chain: bool, archived: bool) -> PortfolioChannel {
+    let spent = spent_amount(channel);
+    PortfolioChannel {
+        channel_id: format!("0x{:x}", channel.channel_id),
+        on_chain,
+        in_sequencer: true,
+        archived,
+        status: Some(channel.effective_status()),
+        capacity: Some(channel.balance.to_string()),
+        spent: Some(spent.to_string()),
+        remaining: Some(channel.balance.saturating_sub(spent).to_string()),
+        expiry_timestamp: Some(channel.expiry_ts),
+        next_sequence_number: Some(channel.sequence_number + 1),
+    }
+}
+
+/// Total allocated to recipients in the latest co-signed state.
+fn spent_amount(channel: &ChannelState) -> U256 {
+    channel
+        .recipients
+        .iter()
+        .fold(U256::zero(), |total, recipient| total.saturating_add(recipient.balance))
+}
+
+/// Channel ids the channel manager lists for `owner`, in contract order.
+async fn owner_channel_ids(state: &AppState, owner_address: Address) -> Result<Vec<String>, AppError> {
     let contract = channel_manager_contract(state.provider.clone(), state.config.channel_manager);
 
     let length: U256 = contract
@@ -307,18 +1667,14 @@ pub async fn list_channels_by_owner(state: &AppState, owner: String) -> Result<C
             .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
         channel_ids.push(format!("0x{:x}", channel_id));
     }
-
-    Ok(ChannelsByOwnerResponse {
-        owner: format!("0x{:x}", owner_address),
-        channel_ids,
-    })
+    Ok(channel_ids)
 }
 
 pub async fn fetch_sequencer_address(
     provider: Arc<Provider<Http>>,
     channel_manager: Address,
 ) -> Result<Address, AppError> {
-    let contract = sequencer_address_contract(provider, channel_manager);
+    let contract = channel_manager_settings_contract(provider, channel_manager);
     contract
         .method::<_, Address>("sequencer", ())
         .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?
@@ -327,6 +1683,70 @@ pub async fn fetch_sequencer_address(
         .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))
 }
 
+pub async fn fetch_payment_asset(
+    provider: Arc<Provider<Http>>,
+    channel_manager: Address,
+) -> Result<Address, AppError> {
+    let contract = channel_manager_settings_contract(provider, channel_manager);
+    contract
+        .method::<_, Address>("token", ())
+        .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?
+        .call()
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))
+}
+
+/// Checks the configured chain id against the RPC endpoint and the local EIP-712 domain
+/// against the channel manager's `eip712Domain()` (EIP-5267), so a wrong `CHAIN_ID` or
+/// `CHANNEL_MANAGER_ADDRESS` fails at startup instead of as rejected signatures. Contracts
+/// without `eip712Domain()` are only checked on the chain id.
+pub async fn resolve_domain(provider: Arc<Provider<Http>>, config: &Config) -> Result<DomainResponse, AppError> {
+    let rpc_chain_id = provider
+        .get_chainid()
+        .await
+        .map_err(|e| AppError::bad_request(format!("rpc error: {e}")))?;
+    if rpc_chain_id != U256::from(config.chain_id) {
+        return Err(AppError::bad_request(format!(
+            "chain id mismatch: config={}, rpc={rpc_chain_id}",
+            config.chain_id
+        )));
+    }
+
+    let local = typed_data_domain(config.chain_id, config.channel_manager);
+    let contract = channel_manager_settings_contract(provider, config.channel_manager);
+    let on_chain = contract
+        .method::<_, ([u8; 1], String, String, U256, Address, [u8; 32], Vec<U256>)>("eip712Domain", ())
+        .map_err(|e| AppError::bad_request(format!("abi error: {e}")))?
+        .call()
+        .await;
+    let verified_on_chain = match on_chain {
+        Ok((_, name, version, chain_id, verifying_contract, _, _)) => {
+            let remote = TypedDataDomain {
+                name,
+                version,
+                chain_id: chain_id.low_u64(),
+                verifying_contract: format!("0x{:x}", verifying_contract),
+            };
+            if remote != local || chain_id > U256::from(u64::MAX) {
+                return Err(AppError::bad_request(format!(
+                    "EIP-712 domain mismatch: local={local:?}, on-chain={remote:?}"
+                )));
+            }
+            true
+        }
+        Err(err) => {
+            warn!(error = %err, "channel manager has no eip712Domain(); domain checked on chain id only");
+            false
+        }
+    };
+
+    Ok(DomainResponse {
+        domain_separator: format!("0x{:x}", domain_separator(config.chain_id, config.channel_manager)),
+        domain: local,
+        verified_on_chain,
+    })
+}
+
 fn channel_manager_contract(
     provider: Arc<Provider<Http>>,
     address: Address,
@@ -334,7 +1754,8 @@ fn channel_manager_contract(
     let abi = ethers_core::abi::Abi::load(
         br#"[
             {"inputs":[{"internalType":"address","name":"owner","type":"address"}],"name":"getUserChannelLength","outputs":[{"internalType":"uint256","name":"","type":"uint256"}],"stateMutability":"view","type":"function"},
-            {"inputs":[{"internalType":"address","name":"","type":"address"},{"internalType":"uint256","name":"","type":"uint256"}],"name":"userChannels","outputs":[{"internalType":"bytes32","name":"","type":"bytes32"}],"stateMutability":"view","type":"function"}
+            {"inputs":[{"internalType":"address","name":"","type":"address"},{"internalType":"uint256","name":"","type":"uint256"}],"name":"userChannels","outputs":[{"internalType":"bytes32","name":"","type":"bytes32"}],"stateMutability":"view","type":"function"},
+            {"inputs":[{"internalType":"bytes32","name":"","type":"bytes32"}],"name":"channels","outputs":[{"internalType":"address","name":"owner","type":"address"},{"internalType":"uint256","name":"balance","type":"uint256"},{"internalType":"uint256","name":"expiryTime","type":"uint256"},{"internalType":"uint256","name":"sequenceNumber","type":"uint256"}],"stateMutability":"view","type":"function"}
         ]"# as &[u8],
     )
     .expect("valid ABI");
@@ -346,21 +1767,60 @@ fn payment_channel_finalize_contract(
     address: Address,
 ) -> ethers_contract::Contract<SignerMiddleware<Provider<Http>, LocalWallet>> {
     let abi = ethers_core::abi::Abi::load(
-        br#"[{"inputs":[{"internalType":"bytes32","name":"channelId","type":"bytes32"},{"internalType":"uint256","name":"sequenceNumber","type":"uint256"},{"internalType":"uint256","name":"timestamp","type":"uint256"},{"internalType":"address[]","name":"recipients","type":"address[]"},{"internalType":"uint256[]","name":"amounts","type":"uint256[]"},{"internalType":"bytes","name":"userSignature","type":"bytes"}],"name":"finalCloseBySequencer","outputs":[],"stateMutability":"nonpayable","type":"function"}]"# as &[u8],
+        br#"[
+            {"inputs":[{"internalType":"bytes32","name":"channelId","type":"bytes32"},{"internalType":"uint256","name":"sequenceNumber","type":"uint256"},{"internalType":"uint256","name":"timestamp","type":"uint256"},{"internalType":"address[]","name":"recipients","type":"address[]"},{"internalType":"uint256[]","name":"amounts","type":"uint256[]"},{"internalType":"bytes","name":"userSignature","type":"bytes"}],"name":"finalCloseBySequencer","outputs":[],"stateMutability":"nonpayable","type":"function"},
+            {"inputs":[{"components":[{"internalType":"bytes32","name":"channelId","type":"bytes32"},{"internalType":"uint256","name":"sequenceNumber","type":"uint256"},{"internalType":"uint256","name":"timestamp","type":"uint256"},{"internalType":"address[]","name":"recipients","type":"address[]"},{"internalType":"uint256[]","name":"amounts","type":"uint256[]"},{"internalType":"bytes","name":"userSignature","type":"bytes"}],"internalType":"struct X402CheddrPaymentChannel.BatchClosure[]","name":"batch","type":"tuple[]"}],"name":"finalCloseBySequencerBatch","outputs":[],"stateMutability":"nonpayable","type":"function"}
+        ]"# as &[u8],
     )
     .expect("valid ABI");
     ethers_contract::Contract::new(address, abi, client)
 }
 
-fn sequencer_address_contract(
+fn channel_manager_settings_contract(
     provider: Arc<Provider<Http>>,
     address: Address,
 ) -> ethers_contract::Contract<Provider<Http>> {
-    let abi = ethers_core::abi::Abi::load(br#"[{"inputs":[],"name":"sequencer","outputs":[{"internalType":"address","name":"","type":"address"}],"stateMutability":"view","type":"function"}]"# as &[u8])
-        .expect("valid ABI");
+    let abi = ethers_core::abi::Abi::load(
+        br#"[
+            {"inputs":[],"name":"sequencer","outputs":[{"internalType":"address","name":"","type":"address"}],"stateMutability":"view","type":"function"},
+            {"inputs":[],"name":"token","outputs":[{"internalType":"contract IERC20","name":"","type":"address"}],"stateMutability":"view","type":"function"},
+            {"inputs":[],"name":"eip712Domain","outputs":[{"internalType":"bytes1","name":"fields","type":"bytes1"},{"internalType":"string","name":"name","type":"string"},{"internalType":"string","name":"version","type":"string"},{"internalType":"uint256","name":"chainId","type":"uint256"},{"internalType":"address","name":"verifyingContract","type":"address"},{"internalType":"bytes32","name":"salt","type":"bytes32"},{"internalType":"uint256[]","name":"extensions","type":"uint256[]"}],"stateMutability":"view","type":"function"}
+        ]"# as &[u8],
+    )
+    .expect("valid ABI");
     ethers_contract::Contract::new(address, abi, provider)
 }
 
+/// The channel's recipients after paying `amount` to `receiver` and the optional fee, in
+/// signing order. Settles and `/prepare` both build the signed arrays here.
+fn pay_recipients(
+    channel: &ChannelState,
+    receiver: &str,
+    amount: &str,
+    fee: Option<&FeeForPayment>,
+) -> Result<Vec<RecipientBalance>, AppError> {
+    let receiver = parse_address(receiver)?;
+    let amount = parse_u256(amount)?;
+    let fee = fee
+        .map(|fee| -> Result<(Address, U256), AppError> {
+            let fee_address = parse_address(&fee.fee_destination_address)?;
+            let fee_amount = parse_u256(&fee.fee_amount_curds)?;
+            Ok((fee_address, fee_amount))
+        })
+        .transpose()?;
+
+    if amount.is_zero() {
+        return Err(AppError::bad_request("amount must be greater than zero"));
+    }
+
+    let mut recipients = channel.recipients.clone();
+    add_amount(&mut recipients, receiver, amount);
+    if let Some((fee_address, fee_amount)) = fee {
+        add_amount(&mut recipients, fee_address, fee_amount);
+    }
+    Ok(recipients)
+}
+
 fn add_amount(recipients: &mut Vec<RecipientBalance>, address: Address, amount: U256) {
     if amount.is_zero() {
         return;
@@ -379,9 +1839,421 @@ fn add_amount(recipients: &mut Vec<RecipientBalance>, address: Address, amount:
     });
 }
 
+/// Persists a channel state, through the group-commit pipeline when it is enabled.
+/// `fencing_token` is the leadership epoch the write was computed under.
+async fn persist_channel(
+    state: &AppState,
+    channel: &ChannelState,
+    fencing_token: Option<u64>,
+) -> Result<(), AppError> {
+    match &state.write_pipeline {
+        Some(pipeline) => pipeline.persist(channel.clone(), fencing_token).await,
+        None => Ok(save_channel(&state.db, channel, fencing_token).await?),
+    }
+}
+
+/// Starts a SIWE login: stores a one-time nonce and returns the EIP-4361 message the
+/// wallet has to sign before `SIWE_NONCE_TTL_SECS` pass.
+pub async fn siwe_nonce(state: &AppState, request: SiweNonceRequest) -> Result<SiweNonceResponse, AppError> {
+    state.leadership.ensure_leader()?;
+    if !state.auth.is_enabled() {
+        return Err(AppError::bad_request("wallet sessions need AUTH_ENABLED"));
+    }
+    let address = parse_address(&request.address)?;
+    let now = now_secs();
+    let expires_at = now + SIWE_NONCE_TTL_SECS;
+    let nonce = hex::encode(rand::random::<[u8; 16]>());
+    let config = &state.config;
+    let message = siwe_message(
+        &config.siwe_domain,
+        &config.siwe_uri,
+        address,
+        config.chain_id,
+        &nonce,
+        now,
+        expires_at,
+    );
+    insert_siwe_nonce(&state.db, &nonce, &format!("0x{:x}", address), &message, expires_at, now).await?;
+
+    Ok(SiweNonceResponse {
+        address: format!("0x{:x}", address),
+        nonce,
+        message,
+        expires_at,
+    })
+}
+
+/// Completes a SIWE login: the nonce must be unexpired and its message signed by the
+/// address it was issued for. Each nonce signs in once.
+pub async fn siwe_login(state: &AppState, request: SiweLoginRequest) -> Result<SessionResponse, AppError> {
+    let (address, message, expires_at) = take_siwe_nonce(&state.db, &request.nonce)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("nonce not found or already used"))?;
+    if u64::try_from(expires_at).unwrap_or(0) < now_secs() {
+        return Err(AppError::unauthorized("nonce expired"));
+    }
+    let address = parse_address(&address)?;
+    let signature = request.signature;
+    let signer = run_blocking(move || {
+        let signature = Signature::from_str(&signature)
+            .map_err(|e| AppError::bad_request(format!("invalid signature: {e}")))?;
+        signature
+            .recover(message.as_str())
+            .map_err(|e| AppError::invalid_signature(format!("signature recovery failed: {e}")))
+    })
+    .await?;
+    if signer != address {
+        return Err(AppError::unauthorized("message not signed by the address"));
+    }
+
+    let (token, expires_at) = state.auth.issue_session(address)?;
+    info!(address = %format!("0x{:x}", address), "wallet signed in");
+    Ok(SessionResponse {
+        address: format!("0x{:x}", address),
+        token,
+        expires_at,
+    })
+}
+
+/// Starts binding a receiver to the caller's key: stores a one-time nonce and returns the
+/// `ReceiverBinding` the receiver has to sign before `RECEIVER_CHALLENGE_TTL_SECS` pass.
+pub async fn receiver_challenge(
+    state: &AppState,
+    caller: Option<&Caller>,
+    request: ReceiverChallengeRequest,
+) -> Result<ReceiverChallengeResponse, AppError> {
+    state.leadership.ensure_leader()?;
+    let caller = binding_caller(caller)?;
+    let receiver = parse_address(&request.receiver)?;
+    let now = now_secs();
+    let expires_at = now + RECEIVER_CHALLENGE_TTL_SECS;
+    let nonce = H256::from(rand::random::<[u8; 32]>());
+    insert_receiver_challenge(
+        &state.db,
+        &format!("0x{:x}", nonce),
+        &caller.key_id,
+        &format!("0x{:x}", receiver),
+        expires_at,
+        now,
+    )
+    .await?;
+
+    Ok(ReceiverChallengeResponse {
+        receiver: format!("0x{:x}", receiver),
+        key_id: caller.key_id.clone(),
+        nonce: format!("0x{:x}", nonce),
+        expires_at,
+        typed_data: receiver_binding_typed_data(
+            receiver,
+            &caller.key_id,
+            nonce,
+            expires_at,
+            state.config.chain_id,
+            state.config.channel_manager,
+        ),
+    })
+}
+
+/// Completes a binding: the challenge must belong to the caller, be unexpired and be
+/// signed by the receiver. Each challenge can be answered once.
+pub async fn bind_receiver(
+    state: &AppState,
+    caller: Option<&Caller>,
+    request: BindReceiverRequest,
+) -> Result<ReceiverBindingView, AppError> {
+    state.leadership.ensure_leader()?;
+    let caller = binding_caller(caller)?;
+    let receiver = parse_address(&request.receiver)?;
+    let nonce = H256::from_str(&request.nonce)
+        .map_err(|_| AppError::bad_request(format!("invalid nonce: {}", request.nonce)))?;
+    let (challenged, expires_at) = take_receiver_challenge(&state.db, &format!("0x{:x}", nonce), &caller.key_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("challenge not found or already used"))?;
+    if parse_address(&challenged)? != receiver {
+        return Err(AppError::bad_request("challenge was issued for another receiver"));
+    }
+    let expires_at = u64::try_from(expires_at).unwrap_or(0);
+    let now = now_secs();
+    if expires_at < now {
+        return Err(AppError::bad_request("challenge expired"));
+    }
+
+    let config = state.config.clone();
+    let key_id = caller.key_id.clone();
+    let signature = request.signature;
+    let signer = run_blocking(move || {
+        recover_receiver_binding(receiver, &key_id, nonce, expires_at, config.chain_id, config.channel_manager, &signature)
+    })
+    .await?;
+    if signer != receiver {
+        return Err(AppError::bad_request("invalid receiver signature"));
+    }
+
+    let bound_at = insert_receiver_binding(&state.db, &caller.key_id, &format!("0x{:x}", receiver), now).await?;
+    state.auth.bind(&caller.key_id, receiver);
+    info!(key_id = %caller.key_id, receiver = %format!("0x{:x}", receiver), "receiver bound");
+    Ok(ReceiverBindingView {
+        receiver: format!("0x{:x}", receiver),
+        bound_at: u64::try_from(bound_at).unwrap_or(0),
+    })
+}
+
+pub async fn receiver_bindings(state: &AppState, caller: Option<&Caller>) -> Result<ReceiverBindingsResponse, AppError> {
+    let caller = binding_caller(caller)?;
+    let receivers = load_receiver_bindings(&state.db, &caller.key_id)
+        .await?
+        .into_iter()
+        .map(|(receiver, bound_at)| ReceiverBindingView {
+            receiver,
+            bound_at: u64::try_from(bound_at).unwrap_or(0),
+        })
+        .collect();
+    Ok(ReceiverBindingsResponse {
+        key_id: caller.key_id.clone(),
+        receivers,
+    })
+}
+
+pub async fn unbind_receiver(state: &AppState, caller: Option<&Caller>, receiver: String) -> Result<(), AppError> {
+    state.leadership.ensure_leader()?;
+    let caller = binding_caller(caller)?;
+    let receiver = parse_address(&receiver)?;
+    if !delete_receiver_binding(&state.db, &caller.key_id, &format!("0x{:x}", receiver)).await? {
+        return Err(AppError::not_found("receiver is not bound to this api key"));
+    }
+    state.auth.unbind(&caller.key_id, receiver);
+    info!(key_id = %caller.key_id, receiver = %format!("0x{:x}", receiver), "receiver unbound");
+    Ok(())
+}
+
+/// Bindings belong to API keys, so they need authentication to be on.
+fn binding_caller(caller: Option<&Caller>) -> Result<&Caller, AppError> {
+    caller.ok_or_else(|| AppError::unauthorized("receiver bindings need AUTH_ENABLED and an api key"))
+}
+
+async fn channel_handle(state: &AppState, channel_id: &str) -> Result<ChannelHandle, AppError> {
+    if let Some(handle) = state.channels.get_or_load(&state.db, channel_id).await? {
+        return Ok(handle);
+    }
+    match quarantine_reason(&state.db, channel_id).await? {
+        Some(reason) => Err(AppError::conflict(format!("channel is quarantined: {reason}"))),
+        None => Err(AppError::not_found("channel not found")),
+    }
+}
+
+/// Rejects signed requests whose timestamp is too far from the server clock, returning now.
+fn check_request_timestamp(timestamp: u64) -> Result<u64, AppError> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if timestamp.abs_diff(now) > SIGNED_REQUEST_MAX_SKEW_SECS {
+        return Err(AppError::bad_request("request timestamp is too far from now"));
+    }
+    Ok(now)
+}
+
+fn channel_key(channel_id: &str) -> Result<String, AppError> {
+    Ok(format!("0x{:x}", parse_h256(channel_id)?))
+}
+
+fn address_key(address: &str) -> Result<String, AppError> {
+    Ok(format!("0x{:x}", parse_address(address)?))
+}
+
 fn parse_signature_bytes(signature: &str) -> Result<Bytes, AppError> {
     let trimmed = signature.strip_prefix("0x").unwrap_or(signature);
     let bytes = hex::decode(trimmed)
         .map_err(|e| AppError::bad_request(format!("invalid signature hex: {e}")))?;
     Ok(Bytes::from(bytes))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RateLimit;
+
+    const CHANNEL_ID: &str = "0x00000000000000000000000000000000000000000000000000000000000000ab";
+
+    fn summary() -> ChannelSummary {
+        ChannelSummary {
+            channel_id: CHANNEL_ID.to_string(),
+            owner: format!("0x{:x}", Address::zero()),
+            balance: "1000".to_string(),
+            remaining_capacity: "400".to_string(),
+            expiry_timestamp: 1_700_000_000,
+            status: ChannelStatus::Open,
+            sequence_number: 3,
+            next_sequence_number: 4,
+            recipient_count: 2,
+            updated_at: 1_650_000_000,
+        }
+    }
+
+    #[test]
+    fn reverted_close_reopens_the_channel() {
+        let outcome = finalize_outcome(TransactionStatus::Reverted, false, true);
+        assert_eq!(outcome, FinalizeOutcome::Reopen);
+        assert_eq!(
+            transition(ChannelStatus::Finalizing, ChannelEvent::FinalizeFailed).unwrap(),
+            ChannelStatus::Open
+        );
+    }
+
+    #[test]
+    fn sent_close_waits_for_its_receipt() {
+        assert_eq!(finalize_outcome(TransactionStatus::Pending, true, true), FinalizeOutcome::Wait);
+        // Not broadcast yet, or dropped: only given up on once the channel is stale.
+        assert_eq!(finalize_outcome(TransactionStatus::None, false, true), FinalizeOutcome::Wait);
+        assert_eq!(finalize_outcome(TransactionStatus::None, true, true), FinalizeOutcome::Reopen);
+        assert_eq!(finalize_outcome(TransactionStatus::Confirmed, false, true), FinalizeOutcome::Close);
+    }
+
+    #[test]
+    fn failed_close_of_a_channel_gone_on_chain_closes_it() {
+        assert_eq!(finalize_outcome(TransactionStatus::Reverted, false, false), FinalizeOutcome::Close);
+        assert_eq!(finalize_outcome(TransactionStatus::None, true, false), FinalizeOutcome::Close);
+    }
+
+    fn wallet(index: u64) -> LocalWallet {
+        format!("{:064x}", index).parse().unwrap()
+    }
+
+    fn payment(signer: &LocalWallet, channel: &ChannelState, config: &Config) -> PayInChannelRequest {
+        let receiver = Address::from_low_u64_be(0xbeef);
+        let timestamp = now_secs();
+        let recipients = [RecipientBalance { recipient_address: receiver, balance: U256::from(100), position: 0 }];
+        let user_signature = sign_update(
+            signer,
+            channel.channel_id,
+            1,
+            timestamp,
+            &recipients,
+            config.chain_id,
+            config.channel_manager,
+        )
+        .unwrap();
+        PayInChannelRequest {
+            channel_id: format!("0x{:x}", channel.channel_id),
+            amount: "100".to_string(),
+            receiver: format!("0x{:x}", receiver),
+            sequence_number: 1,
+            timestamp,
+            user_signature,
+            purpose: None,
+            fee_for_payment: None,
+        }
+    }
+
+    #[test]
+    fn forged_payments_do_not_spend_the_owner_rate_limit() {
+        std::env::set_var("CHANNEL_MANAGER_ADDRESS", "0x5FbDB2315678afecb367f032d93F642f64180aa3");
+        std::env::set_var("SEQUENCER_PRIVATE_KEY", format!("{:064x}", 1));
+        let mut config = Config::from_env().unwrap();
+        config.rate_limit_enabled = true;
+        config.rate_limit_owner = RateLimit { per_sec: 0.001, burst: 1.0 };
+        let limits = RateLimiter::new(&config);
+
+        let owner = wallet(2);
+        let mut channel = crate::db::tests::channel(ChannelStatus::Open, now_secs() + 3_600, 0);
+        channel.owner = owner.address();
+
+        let forged = payment(&wallet(3), &channel, &config);
+        for _ in 0..3 {
+            let result = admit_next_state(&channel, &forged, &config, &limits);
+            assert!(matches!(result, Err(AppError::InvalidSignature(_))));
+        }
+
+        let signed = payment(&owner, &channel, &config);
+        assert!(admit_next_state(&channel, &signed, &config, &limits).is_ok());
+        let result = admit_next_state(&channel, &signed, &config, &limits);
+        assert!(matches!(result, Err(AppError::TooManyRequests { .. })));
+    }
+
+    #[test]
+    fn only_the_owner_session_prepares_updates() {
+        let channel = crate::db::tests::channel(ChannelStatus::Open, 1_700_000_000, 2);
+        let recipient = channel.recipients[0].recipient_address;
+        assert!(ensure_owner(Reader::Wallet(channel.owner), &channel).is_ok());
+        assert!(ensure_owner(Reader::Operator, &channel).is_ok());
+        assert!(matches!(ensure_owner(Reader::Wallet(recipient), &channel), Err(AppError::Forbidden(_))));
+    }
+
+    #[test]
+    fn close_status_is_served_to_participants() {
+        let channel = crate::db::tests::channel(ChannelStatus::Open, 1_700_000_000, 2);
+        let recipient = channel.recipients[1].recipient_address;
+        assert!(ensure_participant(Reader::Wallet(channel.owner), &channel).is_ok());
+        assert!(ensure_participant(Reader::Wallet(recipient), &channel).is_ok());
+        assert!(matches!(
+            ensure_participant(Reader::Wallet(Address::random()), &channel),
+            Err(AppError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn wallet_listing_is_scoped_to_its_channels() {
+        let wallet = Address::random();
+        let key = format!("0x{:x}", wallet);
+
+        let mut filter = ChannelFilter::default();
+        scope_to_reader(Reader::Wallet(wallet), &mut filter).unwrap();
+        assert_eq!(filter.owner.as_deref(), Some(key.as_str()));
+
+        let mut filter = ChannelFilter { recipient: Some(key.clone()), ..ChannelFilter::default() };
+        scope_to_reader(Reader::Wallet(wallet), &mut filter).unwrap();
+        assert_eq!(filter.owner, None);
+
+        let other = format!("0x{:x}", Address::random());
+        let mut filter = ChannelFilter { owner: Some(other.clone()), ..ChannelFilter::default() };
+        assert!(matches!(scope_to_reader(Reader::Wallet(wallet), &mut filter), Err(AppError::Forbidden(_))));
+
+        let mut filter = ChannelFilter { owner: Some(other.clone()), ..ChannelFilter::default() };
+        scope_to_reader(Reader::Operator, &mut filter).unwrap();
+        assert_eq!(filter.owner, Some(other));
+    }
+
+    #[test]
+    fn cursor_round_trips_for_every_sort() {
+        let last = summary();
+        for (sort, key) in [
+            (ChannelSort::ChannelId, CHANNEL_ID),
+            (ChannelSort::Expiry, "1700000000"),
+            (ChannelSort::UpdatedAt, "1650000000"),
+            (ChannelSort::Remaining, "400"),
+        ] {
+            for order in [SortOrder::Asc, SortOrder::Desc] {
+                let cursor = encode_cursor(&last, sort, order);
+                let decoded = decode_cursor(&cursor, sort, order).unwrap();
+                assert_eq!(decoded, (key.to_string(), CHANNEL_ID.to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn cursor_is_bound_to_its_sort_order() {
+        let cursor = encode_cursor(&summary(), ChannelSort::Expiry, SortOrder::Asc);
+        assert!(decode_cursor(&cursor, ChannelSort::Expiry, SortOrder::Desc).is_err());
+        assert!(decode_cursor(&cursor, ChannelSort::UpdatedAt, SortOrder::Asc).is_err());
+    }
+
+    #[test]
+    fn tampered_cursors_are_refused() {
+        let sort = ChannelSort::Expiry;
+        let order = SortOrder::Asc;
+        for tampered in [
+            "not hex".to_string(),
+            hex::encode([0xff, 0xfe]),
+            hex::encode("expiry:asc:1700000000"),
+            hex::encode(format!("expiry:asc:soon:{CHANNEL_ID}")),
+            hex::encode("expiry:asc:1700000000:not-a-channel"),
+        ] {
+            assert!(
+                matches!(decode_cursor(&tampered, sort, order), Err(AppError::BadRequest(_))),
+                "{tampered} should be refused"
+            );
+        }
+        let mismatched = hex::encode(format!("channelId:asc:0x01:{CHANNEL_ID}"));
+        assert!(decode_cursor(&mismatched, ChannelSort::ChannelId, order).is_err());
+    }
+}
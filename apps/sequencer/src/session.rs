@@ -0,0 +1,162 @@
+use chrono::{DateTime, SecondsFormat};
+use ethers_core::{types::Address, utils::hex};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{crypto::parse_address, error::AppError};
+
+/// Prefix that tells wallet session tokens apart from API keys in `Authorization: Bearer`.
+pub const SESSION_TOKEN_PREFIX: &str = "cs_";
+/// How long a SIWE nonce can be signed in with.
+pub const SIWE_NONCE_TTL_SECS: u64 = 300;
+const SIWE_STATEMENT: &str = "Sign in to the CPC sequencer to read your payment channels.";
+
+/// Wallet signed in through SIWE, added to the request extensions.
+#[derive(Debug, Clone, Copy)]
+pub struct Session {
+    pub address: Address,
+}
+
+/// Issues and checks stateless session tokens `cs_<address>_<expiresAt>_<mac>`, where the
+/// mac is an HMAC-SHA256 of `<address>_<expiresAt>`. The key is derived from the sequencer
+/// key, so every node of a deployment accepts the tokens of the others and rotating the
+/// sequencer key ends every session.
+pub struct SessionKeys {
+    key: Vec<u8>,
+    ttl: u64,
+}
+
+impl SessionKeys {
+    pub fn new(sequencer_private_key: &str, ttl_secs: u64) -> Self {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(sequencer_private_key.as_bytes()).expect("hmac accepts any key length");
+        mac.update(b"cpc-sequencer session tokens");
+        Self {
+            key: mac.finalize().into_bytes().to_vec(),
+            ttl: ttl_secs,
+        }
+    }
+
+    pub fn issue(&self, address: Address, now: u64) -> (String, u64) {
+        let expires_at = now + self.ttl;
+        let claims = format!("{}_{expires_at}", hex::encode(address));
+        let token = format!("{SESSION_TOKEN_PREFIX}{claims}_{}", hex::encode(self.mac(&claims)));
+        (token, expires_at)
+    }
+
+    pub fn verify(&self, token: &str, now: u64) -> Result<Session, AppError> {
+        let invalid = || AppError::unauthorized("invalid session token");
+        let (claims, mac) = token
+            .strip_prefix(SESSION_TOKEN_PREFIX)
+            .and_then(|rest| rest.rsplit_once('_'))
+            .ok_or_else(invalid)?;
+        let mac = hex::decode(mac).map_err(|_| invalid())?;
+        let mut expected = Hmac::<Sha256>::new_from_slice(&self.key).expect("hmac accepts any key length");
+        expected.update(claims.as_bytes());
+        expected.verify_slice(&mac).map_err(|_| invalid())?;
+
+        let (address, expires_at) = claims.split_once('_').ok_or_else(invalid)?;
+        let expires_at: u64 = expires_at.parse().map_err(|_| invalid())?;
+        if expires_at < now {
+            return Err(AppError::unauthorized("session expired"));
+        }
+        Ok(Session {
+            address: parse_address(address).map_err(|_| invalid())?,
+        })
+    }
+
+    fn mac(&self, claims: &str) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("hmac accepts any key length");
+        mac.update(claims.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+/// EIP-4361 (Sign-In with Ethereum) message for `address`, signed with `personal_sign`.
+pub fn siwe_message(
+    domain: &str,
+    uri: &str,
+    address: Address,
+    chain_id: u64,
+    nonce: &str,
+    issued_at: u64,
+    expires_at: u64,
+) -> String {
+    format!(
+        "{domain} wants you to sign in with your Ethereum account:\n\
+         {address}\n\
+         \n\
+         {SIWE_STATEMENT}\n\
+         \n\
+         URI: {uri}\n\
+         Version: 1\n\
+         Chain ID: {chain_id}\n\
+         Nonce: {nonce}\n\
+         Issued At: {}\n\
+         Expiration Time: {}",
+        rfc3339(issued_at),
+        rfc3339(expires_at),
+        address = ethers_core::utils::to_checksum(&address, None),
+    )
+}
+
+fn rfc3339(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn keys() -> SessionKeys {
+        SessionKeys::new("0x0123456789abcdef", 3_600)
+    }
+
+    fn address() -> Address {
+        Address::repeat_byte(0x42)
+    }
+
+    fn unauthorized(result: Result<Session, AppError>) -> String {
+        match result {
+            Err(AppError::Unauthorized(message)) => message,
+            other => panic!("expected unauthorized, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn issued_tokens_verify_until_they_expire() {
+        let (token, expires_at) = keys().issue(address(), NOW);
+        assert_eq!(expires_at, NOW + 3_600);
+        assert_eq!(keys().verify(&token, NOW).unwrap().address, address());
+        assert_eq!(keys().verify(&token, expires_at).unwrap().address, address());
+        assert_eq!(unauthorized(keys().verify(&token, expires_at + 1)), "session expired");
+    }
+
+    #[test]
+    fn forged_tokens_are_refused() {
+        let (token, expires_at) = keys().issue(address(), NOW);
+        let (claims, mac) = token.rsplit_once('_').unwrap();
+
+        // Extending the expiry or swapping the address invalidates the mac.
+        let extended = claims.replace(&expires_at.to_string(), &(expires_at + 86_400).to_string());
+        let other = claims.replace(&hex::encode(address()), &hex::encode(Address::repeat_byte(0x07)));
+        // A token from a node with a different sequencer key.
+        let (foreign, _) = SessionKeys::new("0xfedcba9876543210", 3_600).issue(address(), NOW);
+
+        for forged in [
+            format!("{extended}_{mac}"),
+            format!("{other}_{mac}"),
+            format!("{claims}_{}", "00".repeat(32)),
+            format!("{claims}_not-hex"),
+            token.trim_start_matches(SESSION_TOKEN_PREFIX).to_string(),
+            foreign,
+            String::new(),
+        ] {
+            assert_eq!(unauthorized(keys().verify(&forged, NOW)), "invalid session token", "{forged}");
+        }
+    }
+}
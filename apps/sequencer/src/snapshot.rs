@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::{
+    config::Config,
+    crypto::{parse_address, parse_h256, parse_u256},
+    db::{channel_counts, insert_channel_records, load_channel_records},
+    integrity::ChannelVerifier,
+    model::{ChannelRecord, ChannelState, ChannelStatus, RecipientBalance},
+};
+
+/// Bumped whenever the snapshot layout changes incompatibly.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Portable copy of a sequencer store.
+///
+/// The sequencer keeps only the latest signed state per channel, so a snapshot holds one
+/// record per live channel plus the final state of every archived channel.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub version: u32,
+    pub chain_id: u64,
+    pub channel_manager: String,
+    pub exported_at: u64,
+    pub channels: Vec<ChannelRecord>,
+    pub archived_channels: Vec<ChannelRecord>,
+}
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("snapshot io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("snapshot json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("snapshot cbor: {0}")]
+    Cbor(String),
+    #[error("unsupported snapshot version {0} (expected {SNAPSHOT_VERSION})")]
+    UnsupportedVersion(u32),
+    #[error("snapshot is for chain {chain_id} / {channel_manager}, not this deployment")]
+    DomainMismatch { chain_id: u64, channel_manager: String },
+    #[error("store is not empty: {live} live and {archived} archived channels")]
+    StoreNotEmpty { live: i64, archived: i64 },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// Snapshots are CBOR when the file name ends in `.cbor`, JSON otherwise.
+fn is_cbor(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("cbor"))
+}
+
+impl Snapshot {
+    pub fn read(path: &Path) -> Result<Self, SnapshotError> {
+        let reader = BufReader::new(File::open(path)?);
+        let snapshot: Snapshot = if is_cbor(path) {
+            ciborium::from_reader(reader).map_err(|err| SnapshotError::Cbor(err.to_string()))?
+        } else {
+            serde_json::from_reader(reader)?
+        };
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(snapshot.version));
+        }
+        Ok(snapshot)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SnapshotError> {
+        let writer = BufWriter::new(File::create(path)?);
+        if is_cbor(path) {
+            ciborium::into_writer(self, writer).map_err(|err| SnapshotError::Cbor(err.to_string()))?;
+        } else {
+            serde_json::to_writer_pretty(writer, self)?;
+        }
+        Ok(())
+    }
+
+    fn check_domain(&self, config: &Config) -> Result<(), SnapshotError> {
+        let same_manager = parse_address(&self.channel_manager).is_ok_and(|address| address == config.channel_manager);
+        if self.chain_id != config.chain_id || !same_manager {
+            return Err(SnapshotError::DomainMismatch {
+                chain_id: self.chain_id,
+                channel_manager: self.channel_manager.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads every live and archived channel from Postgres into a snapshot.
+pub async fn export(db: &PgPool, config: &Config) -> Result<Snapshot, SnapshotError> {
+    Ok(Snapshot {
+        version: SNAPSHOT_VERSION,
+        chain_id: config.chain_id,
+        channel_manager: format!("0x{:x}", config.channel_manager),
+        exported_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        channels: load_channel_records(db, false).await?,
+        archived_channels: load_channel_records(db, true).await?,
+    })
+}
+
+/// Loads a snapshot into an empty store in one transaction, returning the number of
+/// channels imported. Rows are written verbatim; run `verify` afterwards to check them.
+pub async fn import(db: &PgPool, config: &Config, snapshot: &Snapshot) -> Result<usize, SnapshotError> {
+    snapshot.check_domain(config)?;
+    let (live, archived) = channel_counts(db).await?;
+    if live > 0 || archived > 0 {
+        return Err(SnapshotError::StoreNotEmpty { live, archived });
+    }
+
+    let mut tx = db.begin().await?;
+    insert_channel_records(&mut tx, &snapshot.channels).await?;
+    insert_channel_records(&mut tx, &snapshot.archived_channels).await?;
+    tx.commit().await?;
+    Ok(snapshot.channels.len() + snapshot.archived_channels.len())
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub quarantined: usize,
+    /// `(channel_id, reason)` for every state that failed verification.
+    pub failures: Vec<(String, String)>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Replays every stored state of a snapshot through the checks a settle applies:
+/// strict parsing, recipient limits and capacity, both signatures, and sequence
+/// monotonicity between a channel's archived and live states.
+pub fn verify(snapshot: &Snapshot, verifier: &ChannelVerifier) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    let mut archived_sequences = HashMap::new();
+
+    for record in snapshot.archived_channels.iter().chain(&snapshot.channels) {
+        report.checked += 1;
+        if record.quarantine_reason.is_some() {
+            report.quarantined += 1;
+        }
+
+        let outcome = channel_from_record(record).and_then(|channel| {
+            verifier.verify(&channel)?;
+            match (record.archived_at, archived_sequences.get(&record.channel_id)) {
+                (Some(_), _) => {
+                    archived_sequences.insert(record.channel_id.clone(), channel.sequence_number);
+                }
+                (None, Some(&archived)) if channel.sequence_number < archived => {
+                    return Err(format!(
+                        "live sequence {} is behind archived sequence {archived}",
+                        channel.sequence_number
+                    ));
+                }
+                (None, _) => {}
+            }
+            Ok(())
+        });
+        if let Err(reason) = outcome {
+            report.failures.push((record.channel_id.clone(), reason));
+        }
+    }
+
+    report
+}
+
+fn channel_from_record(record: &ChannelRecord) -> Result<ChannelState, String> {
+    let unsigned = |value: i64, field: &str| u64::try_from(value).map_err(|_| format!("negative {field}: {value}"));
+    let mut recipients = Vec::with_capacity(record.recipients.len());
+    for recipient in &record.recipients {
+        recipients.push(RecipientBalance {
+            recipient_address: parse_address(&recipient.recipient_address).map_err(|err| err.to_string())?,
+            balance: parse_u256(&recipient.balance).map_err(|err| err.to_string())?,
+            position: recipient.position,
+        });
+    }
+
+    Ok(ChannelState {
+        channel_id: parse_h256(&record.channel_id).map_err(|err| err.to_string())?,
+        owner: parse_address(&record.owner).map_err(|err| err.to_string())?,
+        balance: parse_u256(&record.balance).map_err(|err| err.to_string())?,
+        expiry_ts: unsigned(record.expiry_ts, "expiry")?,
+        sequence_number: unsigned(record.sequence_number, "sequence number")?,
+        user_signature: record.user_signature.clone(),
+        sequencer_signature: record.sequencer_signature.clone(),
+        signature_timestamp: unsigned(record.signature_timestamp, "signature timestamp")?,
+        recipients,
+        status: ChannelStatus::parse(&record.status).map_err(|err| err.to_string())?,
+        close_tx_hash: record.close_tx_hash.clone(),
+        closed_at: record.closed_at.map(|ts| unsigned(ts, "close time")).transpose()?,
+    })
+}
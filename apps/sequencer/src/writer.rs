@@ -0,0 +1,174 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use sqlx::PgPool;
+use tokio::sync::{mpsc, oneshot};
+use tracing::error;
+
+use crate::{
+    db::{current_epoch, write_channel, WriteError},
+    error::AppError,
+    model::{ChannelState, WritePipelineMetrics},
+};
+
+/// Group-commit pipeline: settles from many channels are queued and persisted together in
+/// one transaction, either when `max_batch_size` writes are pending or `max_delay` after the
+/// first write of a batch. Each caller waits until the transaction holding its write commits.
+pub struct WritePipeline {
+    sender: mpsc::Sender<PendingWrite>,
+    max_batch_size: usize,
+    max_delay: Duration,
+    counters: Arc<PipelineCounters>,
+}
+
+struct PendingWrite {
+    channel: ChannelState,
+    fencing_token: Option<u64>,
+    enqueued_at: Instant,
+    done: oneshot::Sender<Result<(), AppError>>,
+}
+
+#[derive(Default)]
+struct PipelineCounters {
+    batches: AtomicU64,
+    writes: AtomicU64,
+    failed_batches: AtomicU64,
+    max_batch: AtomicU64,
+    queue_wait_micros: AtomicU64,
+    commit_micros: AtomicU64,
+    last_commit_micros: AtomicU64,
+}
+
+impl WritePipeline {
+    pub fn spawn(db: PgPool, max_batch_size: usize, max_delay: Duration) -> Self {
+        let max_batch_size = max_batch_size.max(1);
+        let (sender, receiver) = mpsc::channel(max_batch_size * 4);
+        let counters = Arc::new(PipelineCounters::default());
+        tokio::spawn(run(db, receiver, max_batch_size, max_delay, counters.clone()));
+        Self {
+            sender,
+            max_batch_size,
+            max_delay,
+            counters,
+        }
+    }
+
+    /// Queues a channel state and resolves once the batch containing it is committed.
+    pub async fn persist(&self, channel: ChannelState, fencing_token: Option<u64>) -> Result<(), AppError> {
+        let (done, committed) = oneshot::channel();
+        let write = PendingWrite {
+            channel,
+            fencing_token,
+            enqueued_at: Instant::now(),
+            done,
+        };
+        if self.sender.send(write).await.is_err() {
+            error!("write pipeline stopped");
+            return Err(AppError::Internal);
+        }
+        committed.await.unwrap_or(Err(AppError::Internal))
+    }
+
+    pub fn metrics(&self) -> WritePipelineMetrics {
+        let batches = self.counters.batches.load(Ordering::Relaxed);
+        let writes = self.counters.writes.load(Ordering::Relaxed);
+        let average = |total: u64, count: u64| {
+            if count == 0 {
+                0.0
+            } else {
+                total as f64 / count as f64
+            }
+        };
+        WritePipelineMetrics {
+            max_batch_size: self.max_batch_size,
+            max_delay_ms: self.max_delay.as_millis() as u64,
+            batches,
+            writes,
+            failed_batches: self.counters.failed_batches.load(Ordering::Relaxed),
+            largest_batch: self.counters.max_batch.load(Ordering::Relaxed),
+            avg_batch_size: average(writes, batches),
+            avg_queue_wait_ms: average(self.counters.queue_wait_micros.load(Ordering::Relaxed), writes) / 1000.0,
+            avg_commit_ms: average(self.counters.commit_micros.load(Ordering::Relaxed), batches) / 1000.0,
+            last_commit_ms: self.counters.last_commit_micros.load(Ordering::Relaxed) as f64 / 1000.0,
+        }
+    }
+}
+
+async fn run(
+    db: PgPool,
+    mut receiver: mpsc::Receiver<PendingWrite>,
+    max_batch_size: usize,
+    max_delay: Duration,
+    counters: Arc<PipelineCounters>,
+) {
+    while let Some(first) = receiver.recv().await {
+        let deadline = tokio::time::Instant::now() + max_delay;
+        let mut batch = vec![first];
+        while batch.len() < max_batch_size {
+            tokio::select! {
+                next = receiver.recv() => match next {
+                    Some(write) => batch.push(write),
+                    None => break,
+                },
+                _ = tokio::time::sleep_until(deadline) => break,
+            }
+        }
+
+        let started = Instant::now();
+        let result = commit(&db, &batch).await;
+        let commit_micros = started.elapsed().as_micros() as u64;
+        let epoch = result.as_ref().ok().and_then(|(epoch, _)| *epoch);
+
+        counters.batches.fetch_add(1, Ordering::Relaxed);
+        counters.writes.fetch_add(batch.len() as u64, Ordering::Relaxed);
+        counters.max_batch.fetch_max(batch.len() as u64, Ordering::Relaxed);
+        counters.commit_micros.fetch_add(commit_micros, Ordering::Relaxed);
+        counters.last_commit_micros.store(commit_micros, Ordering::Relaxed);
+        if let Err(err) = &result {
+            counters.failed_batches.fetch_add(1, Ordering::Relaxed);
+            error!(error = %err, size = batch.len(), "write batch failed");
+        }
+
+        for (index, write) in batch.into_iter().enumerate() {
+            let waited = started.duration_since(write.enqueued_at).as_micros() as u64;
+            counters.queue_wait_micros.fetch_add(waited, Ordering::Relaxed);
+            let outcome = match (&result, write.fencing_token) {
+                (Err(_), _) => Err(AppError::Internal),
+                (Ok(_), Some(token)) if Some(token) != epoch => Err(WriteError::Fenced(token).into()),
+                (Ok((_, quarantined)), _) if quarantined.contains(&index) => {
+                    Err(WriteError::Quarantined(format!("0x{:x}", write.channel.channel_id)).into())
+                }
+                (Ok(_), _) => Ok(()),
+            };
+            let _ = write.done.send(outcome);
+        }
+    }
+}
+
+/// Commits every write whose fencing token is still current, returning the epoch it
+/// checked against (`None` when no write in the batch carried a token) and the positions
+/// of writes refused because their channel was quarantined meanwhile.
+async fn commit(db: &PgPool, batch: &[PendingWrite]) -> Result<(Option<u64>, Vec<usize>), sqlx::Error> {
+    let mut tx = db.begin().await?;
+    let epoch = if batch.iter().any(|write| write.fencing_token.is_some()) {
+        Some(current_epoch(&mut tx, true).await?)
+    } else {
+        None
+    };
+    let mut quarantined = Vec::new();
+    for (index, write) in batch.iter().enumerate() {
+        if write.fencing_token.is_some() && write.fencing_token != epoch {
+            continue;
+        }
+        if !write_channel(&mut tx, &write.channel).await? {
+            quarantined.push(index);
+        }
+    }
+    tx.commit().await?;
+    Ok((epoch, quarantined))
+}
@@ -0,0 +1,367 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ethers_core::types::Address;
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::{
+    crypto::{parse_address, parse_u256},
+    error::AppError,
+    model::{PayInChannelRequest, PaymentRequirements, X402Request, X402SettleResponse},
+};
+
+#[cfg(feature = "x402-v2")]
+pub mod v2;
+
+/// Scheme name of Cheddr payment-channel payments.
+pub const SCHEME: &str = "cpc";
+/// Defaults of generated payment requirements.
+pub const DEFAULT_DESCRIPTION: &str = "Cheddr payment channel (CPC)";
+pub const DEFAULT_MIME_TYPE: &str = "application/json";
+pub const DEFAULT_MAX_TIMEOUT_SECS: u64 = 900;
+
+/// v1 network names that predate CAIP-2 ids, with their chain ids.
+const V1_NETWORK_NAMES: &[(&str, u64)] = &[
+    ("ethereum", 1),
+    ("sepolia", 11_155_111),
+    ("base", 8_453),
+    ("base-sepolia", 84_532),
+    ("polygon", 137),
+    ("polygon-amoy", 80_002),
+    ("avalanche", 43_114),
+    ("avalanche-fuji", 43_113),
+];
+
+/// x402 protocol version of a facilitator request. Responses follow the request's
+/// version; v2 is only understood when built with the `x402-v2` feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X402Version {
+    V1,
+    #[cfg(feature = "x402-v2")]
+    V2,
+}
+
+impl X402Version {
+    pub fn from_number(number: u64) -> Option<Self> {
+        match number {
+            1 => Some(X402Version::V1),
+            #[cfg(feature = "x402-v2")]
+            2 => Some(X402Version::V2),
+            _ => None,
+        }
+    }
+
+    /// Header a resource server returns the encoded settle response in.
+    pub fn payment_response_header(self) -> &'static str {
+        match self {
+            X402Version::V1 => "X-PAYMENT-RESPONSE",
+            #[cfg(feature = "x402-v2")]
+            X402Version::V2 => "PAYMENT-RESPONSE",
+        }
+    }
+}
+
+/// The parts of the payment requirements the `cpc` scheme checks, in every version.
+#[derive(Debug)]
+pub struct Requirements {
+    pub scheme: String,
+    pub network: String,
+    /// `maxAmountRequired` in v1, `amount` in v2.
+    pub amount: String,
+    pub pay_to: String,
+    pub asset: String,
+}
+
+/// A facilitator request decoded from whichever version it was sent in.
+#[derive(Debug)]
+pub struct PaymentRequest {
+    pub version: X402Version,
+    pub scheme: String,
+    pub network: String,
+    pub payload: PayInChannelRequest,
+    pub requirements: Requirements,
+}
+
+impl From<X402Request> for PaymentRequest {
+    fn from(request: X402Request) -> Self {
+        let requirements = request.payment_requirements;
+        PaymentRequest {
+            version: X402Version::V1,
+            scheme: request.payment_payload.scheme,
+            network: request.payment_payload.network,
+            payload: request.payment_payload.payload,
+            requirements: Requirements {
+                scheme: requirements.scheme,
+                network: requirements.network,
+                amount: requirements.max_amount_required,
+                pay_to: requirements.pay_to,
+                asset: requirements.asset,
+            },
+        }
+    }
+}
+
+/// Why a payment was refused. v1 responses carry the message as the reason; v2 responses
+/// carry the code and the message separately.
+#[derive(Debug)]
+pub struct Rejection {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl Rejection {
+    fn new<T: ToString>(code: &'static str, message: T) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    /// Maps a refused channel update to a rejection. Faults of the sequencer itself are
+    /// not the payment's fault and stay HTTP errors, so clients retry them.
+    pub fn from_error(err: AppError) -> Result<Self, AppError> {
+        let code = match err {
+            AppError::BadRequest(_) | AppError::InvalidSignature(_) => "invalid_cpc_payload",
+            AppError::NotFound(_) => "invalid_cpc_channel",
+            AppError::Conflict(_) => "invalid_cpc_channel_state",
+            err => return Err(err),
+        };
+        Ok(Self::new(code, err))
+    }
+
+    pub fn reason(&self, version: X402Version) -> String {
+        match version {
+            X402Version::V1 => self.message.clone(),
+            #[cfg(feature = "x402-v2")]
+            X402Version::V2 => self.code.to_string(),
+        }
+    }
+
+    pub fn detail(&self, version: X402Version) -> Option<String> {
+        match version {
+            X402Version::V1 => None,
+            #[cfg(feature = "x402-v2")]
+            X402Version::V2 => Some(self.message.clone()),
+        }
+    }
+}
+
+/// CAIP-2 network id of the chain the channel manager lives on.
+pub fn network(chain_id: u64) -> String {
+    format!("eip155:{chain_id}")
+}
+
+/// Chain id of a network string: CAIP-2 `eip155:<chainId>`, or a legacy name in v1.
+pub fn chain_id(network: &str, version: X402Version) -> Option<u64> {
+    if let Some(reference) = network.strip_prefix("eip155:") {
+        return reference.parse().ok();
+    }
+    match version {
+        X402Version::V1 => V1_NETWORK_NAMES
+            .iter()
+            .find(|(name, _)| *name == network)
+            .map(|(_, chain_id)| *chain_id),
+        #[cfg(feature = "x402-v2")]
+        X402Version::V2 => None,
+    }
+}
+
+/// The version a request body asks for, falling back to v1 so that an unsupported
+/// version is reported in the v1 shape.
+pub fn requested_version(body: &Value) -> X402Version {
+    body.get("x402Version")
+        .and_then(Value::as_u64)
+        .and_then(X402Version::from_number)
+        .unwrap_or(X402Version::V1)
+}
+
+/// Decodes a facilitator request body of any enabled version.
+pub fn decode(body: Value) -> Result<PaymentRequest, Rejection> {
+    let number = body.get("x402Version").and_then(Value::as_u64).unwrap_or(0);
+    match X402Version::from_number(number) {
+        Some(X402Version::V1) => {
+            let request: X402Request =
+                serde_json::from_value(body).map_err(|err| Rejection::new("invalid_payload", err))?;
+            if request.payment_payload.x402_version != 1 {
+                return Err(Rejection::new(
+                    "invalid_x402_version",
+                    "paymentPayload and request x402Version differ",
+                ));
+            }
+            Ok(request.into())
+        }
+        #[cfg(feature = "x402-v2")]
+        Some(X402Version::V2) => v2::decode(body),
+        None => Err(Rejection::new(
+            "invalid_x402_version",
+            format!("unsupported x402Version {number}"),
+        )),
+    }
+}
+
+/// Checks that a payment is for this deployment and satisfies the requirements it was
+/// built from. The channel update itself is checked by `validate_pay_in_channel`.
+pub fn check(request: &PaymentRequest, chain_id: u64, asset: Address) -> Result<(), Rejection> {
+    let requirements = &request.requirements;
+    if request.scheme != SCHEME || requirements.scheme != SCHEME {
+        return Err(Rejection::new("invalid_scheme", format!("unsupported scheme (expected {SCHEME})")));
+    }
+    let on_chain = |network: &str| self::chain_id(network, request.version) == Some(chain_id);
+    if !on_chain(&request.network) || !on_chain(&requirements.network) {
+        return Err(Rejection::new(
+            "invalid_network",
+            format!("unsupported network (expected {})", network(chain_id)),
+        ));
+    }
+    let invalid = |err: AppError| Rejection::new("invalid_payload", err);
+    if parse_address(&requirements.asset).map_err(invalid)? != asset {
+        return Err(Rejection::new(
+            "invalid_payment_requirements",
+            "asset does not match the channel token",
+        ));
+    }
+    if parse_address(&requirements.pay_to).map_err(invalid)? != parse_address(&request.payload.receiver).map_err(invalid)? {
+        return Err(Rejection::new(
+            "invalid_cpc_payload_recipient_mismatch",
+            "receiver does not match payTo",
+        ));
+    }
+    if parse_u256(&request.payload.amount).map_err(invalid)? < parse_u256(&requirements.amount).map_err(invalid)? {
+        return Err(Rejection::new(
+            "invalid_cpc_payload_amount",
+            "amount is less than the required amount",
+        ));
+    }
+    Ok(())
+}
+
+/// Base64 JSON of a settle response, as resource servers return it to the client.
+pub fn payment_response_header(response: &X402SettleResponse) -> String {
+    STANDARD.encode(serde_json::to_vec(response).expect("settle response serializes"))
+}
+
+/// `/x402/requirements` answer: the v1 `accepts` entry, or with `x402Version: 2` the v2
+/// `PaymentRequired` body, which carries the resource next to the entry.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum X402RequirementsResponse {
+    V1(PaymentRequirements),
+    #[cfg(feature = "x402-v2")]
+    V2(v2::PaymentRequired),
+}
+
+impl X402RequirementsResponse {
+    pub fn new(requirements: PaymentRequirements, version: X402Version) -> Self {
+        match version {
+            X402Version::V1 => X402RequirementsResponse::V1(requirements),
+            #[cfg(feature = "x402-v2")]
+            X402Version::V2 => X402RequirementsResponse::V2(requirements.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const CHAIN_ID: u64 = 84_532;
+    const ASSET: &str = "0x1111111111111111111111111111111111111111";
+    const RECEIVER: &str = "0x2222222222222222222222222222222222222222";
+
+    fn body() -> Value {
+        json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "cpc",
+                "network": "base-sepolia",
+                "payload": {
+                    "channelId": format!("0x{}", "ab".repeat(32)),
+                    "amount": "100",
+                    "receiver": RECEIVER,
+                    "sequenceNumber": 1,
+                    "timestamp": 1_700_000_000u64,
+                    "userSignature": "0x00",
+                },
+            },
+            "paymentRequirements": {
+                "scheme": "cpc",
+                "network": format!("eip155:{CHAIN_ID}"),
+                "maxAmountRequired": "100",
+                "resource": "https://example.com/resource",
+                "payTo": RECEIVER,
+                "maxTimeoutSeconds": 60,
+                "asset": ASSET,
+            },
+        })
+    }
+
+    fn checked(body: Value) -> Result<(), &'static str> {
+        let request = decode(body).map_err(|rejection| rejection.code)?;
+        check(&request, CHAIN_ID, ASSET.parse().unwrap()).map_err(|rejection| rejection.code)
+    }
+
+    #[test]
+    fn accepts_a_matching_payment() {
+        assert_eq!(checked(body()), Ok(()));
+    }
+
+    #[test]
+    fn decode_rejects_versions() {
+        let mut unsupported = body();
+        unsupported["x402Version"] = json!(7);
+        assert_eq!(checked(unsupported), Err("invalid_x402_version"));
+
+        let mut mismatched = body();
+        mismatched["paymentPayload"]["x402Version"] = json!(2);
+        assert_eq!(checked(mismatched), Err("invalid_x402_version"));
+
+        let mut malformed = body();
+        malformed["paymentPayload"]["payload"] = json!({});
+        assert_eq!(checked(malformed), Err("invalid_payload"));
+    }
+
+    #[test]
+    fn check_rejects_mismatches() {
+        let cases: [(&str, &str, Value, &str); 6] = [
+            ("paymentPayload", "scheme", json!("exact"), "invalid_scheme"),
+            ("paymentRequirements", "scheme", json!("exact"), "invalid_scheme"),
+            ("paymentPayload", "network", json!("base"), "invalid_network"),
+            ("paymentRequirements", "network", json!("eip155:1"), "invalid_network"),
+            (
+                "paymentRequirements",
+                "asset",
+                json!("0x3333333333333333333333333333333333333333"),
+                "invalid_payment_requirements",
+            ),
+            (
+                "paymentRequirements",
+                "payTo",
+                json!("0x3333333333333333333333333333333333333333"),
+                "invalid_cpc_payload_recipient_mismatch",
+            ),
+        ];
+        for (object, field, value, code) in cases {
+            let mut body = body();
+            body[object][field] = value;
+            assert_eq!(checked(body), Err(code), "{object}.{field}");
+        }
+
+        let mut short = body();
+        short["paymentRequirements"]["maxAmountRequired"] = json!("101");
+        assert_eq!(checked(short), Err("invalid_cpc_payload_amount"));
+
+        let mut bad_asset = body();
+        bad_asset["paymentRequirements"]["asset"] = json!("not an address");
+        assert_eq!(checked(bad_asset), Err("invalid_payload"));
+    }
+
+    #[test]
+    fn signature_errors_are_payload_rejections() {
+        let rejection = Rejection::from_error(AppError::invalid_signature("invalid user signature")).unwrap();
+        assert_eq!(rejection.code, "invalid_cpc_payload");
+        assert!(Rejection::from_error(AppError::Internal).is_err());
+    }
+}
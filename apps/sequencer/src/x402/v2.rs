@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::{PaymentRequest, Rejection, Requirements, X402Version};
+use crate::model::{PayInChannelRequest, PaymentRequirements};
+
+/// Resource metadata, which v2 moved out of the payment requirements.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceInfo {
+    pub url: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: String,
+}
+
+/// One entry of a v2 `accepts` list; `maxAmountRequired` became `amount`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirementsV2 {
+    pub scheme: String,
+    /// CAIP-2 network id.
+    pub network: String,
+    pub amount: String,
+    pub asset: String,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    #[schema(value_type = Option<Object>)]
+    pub extra: Option<Value>,
+}
+
+/// Decoded `PAYMENT-SIGNATURE` header: the payload plus the requirements it accepted.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentPayloadV2 {
+    pub x402_version: u32,
+    pub resource: Option<ResourceInfo>,
+    pub accepted: PaymentRequirementsV2,
+    pub payload: PayInChannelRequest,
+    #[schema(value_type = Option<Object>)]
+    pub extensions: Option<Value>,
+}
+
+/// x402 v2 facilitator request body for `/x402/verify` and `/x402/settle`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct X402RequestV2 {
+    pub x402_version: u32,
+    pub payment_payload: PaymentPayloadV2,
+    pub payment_requirements: PaymentRequirementsV2,
+}
+
+/// Body of a v2 `402` response, sent base64-encoded as `PAYMENT-REQUIRED`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequired {
+    pub x402_version: u32,
+    pub error: Option<String>,
+    pub resource: ResourceInfo,
+    pub accepts: Vec<PaymentRequirementsV2>,
+}
+
+impl From<PaymentRequirements> for PaymentRequired {
+    fn from(requirements: PaymentRequirements) -> Self {
+        PaymentRequired {
+            x402_version: 2,
+            error: None,
+            resource: ResourceInfo {
+                url: requirements.resource,
+                description: requirements.description,
+                mime_type: requirements.mime_type,
+            },
+            accepts: vec![PaymentRequirementsV2 {
+                scheme: requirements.scheme,
+                network: requirements.network,
+                amount: requirements.max_amount_required,
+                asset: requirements.asset,
+                pay_to: requirements.pay_to,
+                max_timeout_seconds: requirements.max_timeout_seconds,
+                extra: requirements.extra,
+            }],
+        }
+    }
+}
+
+/// v2 payloads repeat the requirements they were built for; a payload that accepted
+/// different terms than the server now asks for is refused.
+pub(super) fn decode(body: Value) -> Result<PaymentRequest, Rejection> {
+    let request: X402RequestV2 =
+        serde_json::from_value(body).map_err(|err| Rejection::new("invalid_payload", err))?;
+    if request.payment_payload.x402_version != 2 {
+        return Err(Rejection::new(
+            "invalid_x402_version",
+            "paymentPayload and request x402Version differ",
+        ));
+    }
+    let accepted = request.payment_payload.accepted;
+    let requirements = request.payment_requirements;
+    if accepted.scheme != requirements.scheme
+        || accepted.network != requirements.network
+        || accepted.amount != requirements.amount
+        || accepted.asset != requirements.asset
+        || accepted.pay_to != requirements.pay_to
+    {
+        return Err(Rejection::new(
+            "invalid_payment_requirements",
+            "accepted requirements do not match paymentRequirements",
+        ));
+    }
+
+    Ok(PaymentRequest {
+        version: X402Version::V2,
+        scheme: accepted.scheme,
+        network: accepted.network,
+        payload: request.payment_payload.payload,
+        requirements: Requirements {
+            scheme: requirements.scheme,
+            network: requirements.network,
+            amount: requirements.amount,
+            pay_to: requirements.pay_to,
+            asset: requirements.asset,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::x402::check;
+
+    const ASSET: &str = "0x1111111111111111111111111111111111111111";
+    const RECEIVER: &str = "0x2222222222222222222222222222222222222222";
+
+    fn requirements() -> Value {
+        json!({
+            "scheme": "cpc",
+            "network": "eip155:84532",
+            "amount": "100",
+            "asset": ASSET,
+            "payTo": RECEIVER,
+            "maxTimeoutSeconds": 60,
+        })
+    }
+
+    fn body() -> Value {
+        json!({
+            "x402Version": 2,
+            "paymentPayload": {
+                "x402Version": 2,
+                "accepted": requirements(),
+                "payload": {
+                    "channelId": format!("0x{}", "ab".repeat(32)),
+                    "amount": "100",
+                    "receiver": RECEIVER,
+                    "sequenceNumber": 1,
+                    "timestamp": 1_700_000_000u64,
+                    "userSignature": "0x00",
+                },
+            },
+            "paymentRequirements": requirements(),
+        })
+    }
+
+    #[test]
+    fn decodes_a_matching_payment() {
+        let request = decode(body()).unwrap();
+        assert_eq!(request.version, X402Version::V2);
+        assert!(check(&request, 84_532, ASSET.parse().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn rejects_accepted_requirements_that_differ() {
+        for (field, value) in [
+            ("scheme", json!("exact")),
+            ("network", json!("eip155:1")),
+            ("amount", json!("1")),
+            ("asset", json!(RECEIVER)),
+            ("payTo", json!(ASSET)),
+        ] {
+            let mut body = body();
+            body["paymentPayload"]["accepted"][field] = value;
+            let rejection = decode(body).unwrap_err();
+            assert_eq!(rejection.code, "invalid_payment_requirements", "{field}");
+        }
+    }
+
+    #[test]
+    fn rejects_legacy_network_names() {
+        let mut body = body();
+        body["paymentPayload"]["accepted"]["network"] = json!("base-sepolia");
+        body["paymentRequirements"]["network"] = json!("base-sepolia");
+        let request = decode(body).unwrap();
+        assert_eq!(check(&request, 84_532, ASSET.parse().unwrap()).unwrap_err().code, "invalid_network");
+    }
+
+    #[test]
+    fn rejects_a_payload_version_mismatch() {
+        let mut body = body();
+        body["paymentPayload"]["x402Version"] = json!(1);
+        assert_eq!(decode(body).unwrap_err().code, "invalid_x402_version");
+    }
+}